@@ -0,0 +1,152 @@
+use std::collections::BTreeSet;
+
+use crate::expression::{BinaryExpression, BinaryOperator, EvaluationContext, Expression, free_variables, UnaryExpression, UnaryOperator, Value, Variable};
+
+/// The maximum number of variables, the transformations work on the full truth table.
+const MAX_VARIABLES: usize = 24;
+
+/// Returns the free variables and the values of the expression for all their assignments; bit
+/// `j` of the index of a value is the value of the `j`th variable.
+pub fn truth_table(expr: &dyn Expression) -> Result<(Vec<String>, Vec<bool>), String> {
+    let free = free_variables(expr);
+    if free.len() > MAX_VARIABLES {
+        return Err(format!("the expression has more than {} variables", MAX_VARIABLES));
+    }
+    let variables: Vec<String> = free.iter().cloned().collect();
+    let mut ctxt = EvaluationContext::new(free);
+    let table = (0..(1usize << variables.len())).map(|i| {
+        ctxt.set_not_presets(i as u128);
+        expr.eval(&ctxt)
+    }).collect();
+    Ok((variables, table))
+}
+
+/*
+ * Anf
+ */
+
+/// A fixed-polarity Reed–Muller form, an XOR of AND-monomials of literals; each variable occurs
+/// either only positive or only negated. With all variables positive this is the algebraic normal
+/// form (Zhegalkin polynomial).
+pub struct Anf {
+    pub variables: Vec<String>,
+    /// The variables that occur negated
+    pub negated: BTreeSet<String>,
+    /// The coefficient of each monomial, bit `j` of the index tells whether the monomial contains
+    /// the `j`th variable
+    pub coefficients: Vec<bool>,
+}
+
+impl Anf {
+    /// Computes the Reed–Muller form in which the given variables occur negated by the Möbius
+    /// transform of the truth table; without negated variables this is the algebraic normal form.
+    pub fn with_polarity(expr: &dyn Expression, negated: &BTreeSet<String>) -> Result<Anf, String> {
+        let (variables, table) = truth_table(expr)?;
+        if let Some(var) = negated.iter().find(|var| !variables.contains(*var)) {
+            return Err(format!("variable '{}' does not occur in the expression", var));
+        }
+
+        // The truth table of the function over the literals, i.e. with the negated variables flipped
+        let flipped = variables.iter().enumerate()
+            .filter(|(_, var)| negated.contains(*var))
+            .fold(0usize, |mask, (bit, _)| mask | (1 << bit));
+        let mut coefficients: Vec<bool> = (0..table.len()).map(|i| table[i ^ flipped]).collect();
+
+        // Möbius transform: each coefficient becomes the XOR over the values of its submonomials
+        for bit in 0..variables.len() {
+            for i in 0..coefficients.len() {
+                if i & (1 << bit) != 0 {
+                    coefficients[i] ^= coefficients[i ^ (1 << bit)];
+                }
+            }
+        }
+        Ok(Anf { variables, negated: negated.clone(), coefficients })
+    }
+
+    /// Returns the algebraic degree, the size of the largest monomial; `0` for constants.
+    pub fn degree(&self) -> usize {
+        self.monomials().iter().map(|monomial| monomial.count_ones() as usize).max().unwrap_or(0)
+    }
+
+    /// Returns the monomials with coefficient `1`, smaller monomials first.
+    pub fn monomials(&self) -> Vec<usize> {
+        let mut monomials: Vec<usize> = (0..self.coefficients.len()).filter(|i| self.coefficients[*i]).collect();
+        let literals = |monomial: &usize| (0..self.variables.len()).filter(|bit| monomial & (1 << bit) != 0).collect::<Vec<_>>();
+        monomials.sort_by_key(|monomial| (monomial.count_ones(), literals(monomial)));
+        monomials
+    }
+
+    /// Returns the form as expression using `^` and `&`, e.g. `1 ^ a ^ a & b`.
+    pub fn to_expression(&self) -> Box<dyn Expression> {
+        let literal = |bit: usize| -> Box<dyn Expression> {
+            let var = Box::new(Variable::new(&self.variables[bit]));
+            if self.negated.contains(&self.variables[bit]) { Box::new(UnaryExpression::new(UnaryOperator::NEG, var)) } else { var }
+        };
+        let monomial = |monomial: usize| -> Box<dyn Expression> {
+            let mut literals = (0..self.variables.len()).filter(|bit| monomial & (1 << bit) != 0).map(literal);
+            match literals.next() {
+                Some(first) => literals.fold(first, |left, right| Box::new(BinaryExpression::new(BinaryOperator::AND, left, right))),
+                None => Box::new(Value::new(true))
+            }
+        };
+        let mut monomials = self.monomials().into_iter().map(monomial);
+        match monomials.next() {
+            Some(first) => monomials.fold(first, |left, right| Box::new(BinaryExpression::new(BinaryOperator::XOR, left, right))),
+            None => Box::new(Value::new(false))
+        }
+    }
+}
+
+/*
+ * Tests
+ */
+
+#[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
+mod tests {
+    use super::*;
+    use crate::expression::assert_equivalent;
+    use crate::parser::parse;
+    use crate::tokens::tokenize;
+
+    fn anf(str: &str, negated: &[&str]) -> Anf {
+        let expr = parse(&tokenize(str).unwrap_or_else(|_| vec![])).ok().unwrap();
+        let negated: BTreeSet<String> = negated.iter().map(|var| var.to_string()).collect();
+        let anf = Anf::with_polarity(expr.as_ref(), &negated).ok().unwrap();
+
+        // The form must be equivalent to the expression
+        assert_equivalent(expr.as_ref(), anf.to_expression().as_ref());
+        anf
+    }
+
+    #[test]
+    fn with_polarity_computes_the_anf() {
+        assert_eq!(anf("a | b", &[]).to_expression().to_string(), "(a ^ b) ^ a & b");
+        assert_eq!(anf("!a", &[]).to_expression().to_string(), "1 ^ a");
+        assert_eq!(anf("a = b", &[]).to_expression().to_string(), "(1 ^ a) ^ b");
+        assert_eq!(anf("maj(a, b, c)", &[]).to_expression().to_string(), "(a & b ^ a & c) ^ b & c");
+        assert_eq!(anf("a & !a", &[]).to_expression().to_string(), "0");
+        assert_eq!(anf("a | !a", &[]).to_expression().to_string(), "1");
+    }
+
+    #[test]
+    fn with_polarity_negates_the_variables() {
+        assert_eq!(anf("!a", &["a"]).to_expression().to_string(), "!a");
+        assert_eq!(anf("a | b", &["a", "b"]).to_expression().to_string(), "1 ^ !a & !b");
+        assert_eq!(anf("a | b", &["a"]).to_expression().to_string(), "(1 ^ !a) ^ !a & b");
+
+        let expr = parse(&tokenize("a | b").unwrap_or_else(|_| vec![])).ok().unwrap();
+        let mut negated = BTreeSet::new();
+        negated.insert(String::from("c"));
+        assert_eq!(Anf::with_polarity(expr.as_ref(), &negated).err().unwrap(), "variable 'c' does not occur in the expression");
+    }
+
+    #[test]
+    fn degree_is_the_size_of_the_largest_monomial() {
+        assert_eq!(anf("a | b", &[]).degree(), 2);
+        assert_eq!(anf("a ^ b ^ c", &[]).degree(), 1);
+        assert_eq!(anf("a | b", &["a"]).degree(), 2);
+        assert_eq!(anf("a & b & c | a", &[]).degree(), 1);
+        assert_eq!(anf("0", &[]).degree(), 0);
+    }
+}
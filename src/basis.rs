@@ -0,0 +1,254 @@
+use std::collections::BTreeMap;
+
+use crate::expression::{BinaryExpression, BinaryOperator, expand_cardinality, Expression, Quantifier, replace_child, UnaryExpression, UnaryOperator, Value};
+use crate::simplify::simplify;
+
+/// A set of operators that is functionally complete on its own.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Basis {
+    /// Only `!&`
+    Nand,
+    /// Only `!|`
+    Nor,
+    /// Only `!` and `&`
+    NotAnd,
+    /// Only `!` and `=>`
+    NotImp,
+}
+
+/// The smallest known expressions for a function and its negation.
+struct Pair {
+    pos: Box<dyn Expression>,
+    neg: Box<dyn Expression>,
+}
+
+impl Pair {
+    fn swap(self) -> Pair {
+        Pair { pos: self.neg, neg: self.pos }
+    }
+}
+
+/*
+ * Basis
+ */
+
+impl Basis {
+    pub fn from_name(name: &str) -> Option<Basis> {
+        match name {
+            "nand" => Some(Basis::Nand),
+            "nor" => Some(Basis::Nor),
+            "not-and" => Some(Basis::NotAnd),
+            "not-imp" => Some(Basis::NotImp),
+            _ => None
+        }
+    }
+
+    /// Rewrites the expression using only the operators of the basis. Quantifiers and counting
+    /// functions are expanded and the expression is simplified first; then the smallest of the
+    /// alternative translations of each operator is chosen, taking into account that a negated
+    /// operand may be cheaper than the operand itself. Constants only remain if the whole
+    /// expression is constant.
+    pub fn rewrite(&self, expr: &dyn Expression) -> Box<dyn Expression> {
+        let expr = simplify(expand(expr).as_ref());
+        if expr.as_value().is_some() {
+            return expr;
+        }
+        self.translate(expr.as_ref()).pos
+    }
+
+    fn translate(&self, expr: &dyn Expression) -> Pair {
+        if let Some(unary) = expr.as_unary() {
+            return self.translate(unary.arg.as_ref()).swap();
+        }
+        if let Some(binary) = expr.as_binary() {
+            let (x, y) = (self.translate(binary.left.as_ref()), self.translate(binary.right.as_ref()));
+            return match binary.op {
+                BinaryOperator::AND => self.and(&x, &y),
+                BinaryOperator::OR => self.or(&x, &y),
+                BinaryOperator::IMP => self.or(&copy(&x).swap(), &y),
+                BinaryOperator::XOR => self.xor(&x, &y),
+                BinaryOperator::EQ => self.xor(&x, &y).swap(),
+                // The other operators are replaced by the simplification
+                _ => panic!("unexpected operator {:?}", binary.op)
+            };
+        }
+        if let Some(conditional) = expr.as_conditional() {
+            let c = self.translate(conditional.condition.as_ref());
+            let (t, f) = (self.translate(conditional.if_true.as_ref()), self.translate(conditional.if_false.as_ref()));
+            let not_c = copy(&c).swap();
+            let sum_of_products = self.or(&self.and(&c, &t), &self.and(&not_c, &f));
+            let product_of_sums = self.and(&self.or(&not_c, &t), &self.or(&c, &f));
+            return smallest(vec![sum_of_products, product_of_sums]);
+        }
+        self.with_pos(expr.clone_box())
+    }
+
+    fn xor(&self, x: &Pair, y: &Pair) -> Pair {
+        let (not_x, not_y) = (copy(x).swap(), copy(y).swap());
+        smallest(vec![
+            self.or(&self.and(x, &not_y), &self.and(&not_x, y)),
+            self.and(&self.or(x, y), &self.or(&not_x, &not_y)),
+            self.or(&self.and(x, y), &self.and(&not_x, &not_y)).swap(),
+            self.and(&self.or(x, &not_y), &self.or(&not_x, y)).swap(),
+        ])
+    }
+
+    fn and(&self, x: &Pair, y: &Pair) -> Pair {
+        match self {
+            Basis::Nand => self.with_neg(binary(BinaryOperator::NAND, x.pos.as_ref(), y.pos.as_ref())),
+            Basis::Nor => self.with_pos(binary(BinaryOperator::NOR, x.neg.as_ref(), y.neg.as_ref())),
+            Basis::NotAnd => self.with_pos(binary(BinaryOperator::AND, x.pos.as_ref(), y.pos.as_ref())),
+            Basis::NotImp => self.with_neg(binary(BinaryOperator::IMP, x.pos.as_ref(), y.neg.as_ref()))
+        }
+    }
+
+    fn or(&self, x: &Pair, y: &Pair) -> Pair {
+        match self {
+            Basis::Nand => self.with_pos(binary(BinaryOperator::NAND, x.neg.as_ref(), y.neg.as_ref())),
+            Basis::Nor => self.with_neg(binary(BinaryOperator::NOR, x.pos.as_ref(), y.pos.as_ref())),
+            Basis::NotAnd => self.with_neg(binary(BinaryOperator::AND, x.neg.as_ref(), y.neg.as_ref())),
+            Basis::NotImp => smallest(vec![
+                self.with_pos(binary(BinaryOperator::IMP, x.neg.as_ref(), y.pos.as_ref())),
+                self.with_pos(binary(BinaryOperator::IMP, y.neg.as_ref(), x.pos.as_ref())),
+            ])
+        }
+    }
+
+    fn not(&self, expr: &dyn Expression) -> Box<dyn Expression> {
+        match self {
+            Basis::Nand => Box::new(BinaryExpression::new(BinaryOperator::NAND, expr.clone_box(), expr.clone_box())),
+            Basis::Nor => Box::new(BinaryExpression::new(BinaryOperator::NOR, expr.clone_box(), expr.clone_box())),
+            Basis::NotAnd | Basis::NotImp => Box::new(UnaryExpression::new(UnaryOperator::NEG, expr.clone_box()))
+        }
+    }
+
+    fn with_pos(&self, pos: Box<dyn Expression>) -> Pair {
+        Pair { neg: self.not(pos.as_ref()), pos }
+    }
+
+    fn with_neg(&self, neg: Box<dyn Expression>) -> Pair {
+        Pair { pos: self.not(neg.as_ref()), neg }
+    }
+}
+
+/// Replaces quantifiers and counting functions by expressions over the other operators.
+fn expand(expr: &dyn Expression) -> Box<dyn Expression> {
+    if let Some(cardinality) = expr.as_cardinality() {
+        return expand(expand_cardinality(cardinality).as_ref());
+    }
+    if let Some(quantified) = expr.as_quantified() {
+        let cofactor = |value: bool| {
+            let mut bindings: BTreeMap<String, Box<dyn Expression>> = BTreeMap::new();
+            bindings.insert(quantified.var.clone(), Box::new(Value::new(value)));
+            expand(quantified.body.substitute(&bindings).as_ref())
+        };
+        let op = if quantified.quantifier == Quantifier::EXISTS { BinaryOperator::OR } else { BinaryOperator::AND };
+        return Box::new(BinaryExpression::new(op, cofactor(false), cofactor(true)));
+    }
+    let mut result = expr.clone_box();
+    for (index, child) in expr.children().into_iter().enumerate() {
+        result = replace_child(result.as_ref(), index, expand(child));
+    }
+    result
+}
+
+/// Returns the pair of the smallest expressions among the candidates.
+fn smallest(candidates: Vec<Pair>) -> Pair {
+    let mut candidates = candidates.into_iter();
+    let first = candidates.next().unwrap();
+    candidates.fold(first, |best, candidate| Pair {
+        pos: if size(candidate.pos.as_ref()) < size(best.pos.as_ref()) { candidate.pos } else { best.pos },
+        neg: if size(candidate.neg.as_ref()) < size(best.neg.as_ref()) { candidate.neg } else { best.neg },
+    })
+}
+
+/// Returns the number of operators and operands of the expression.
+pub fn size(expr: &dyn Expression) -> usize {
+    1 + expr.children().into_iter().map(size).sum::<usize>()
+}
+
+fn copy(pair: &Pair) -> Pair {
+    Pair { pos: pair.pos.clone_box(), neg: pair.neg.clone_box() }
+}
+
+fn binary(op: BinaryOperator, left: &dyn Expression, right: &dyn Expression) -> Box<dyn Expression> {
+    Box::new(BinaryExpression::new(op, left.clone_box(), right.clone_box()))
+}
+
+/*
+ * Tests
+ */
+
+#[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
+mod tests {
+    use super::*;
+    use crate::expression::assert_equivalent;
+    use crate::parser::parse;
+    use crate::tokens::tokenize;
+
+    fn rewritten(basis: Basis, str: &str) -> String {
+        let expr = parse(&tokenize(str).unwrap_or_else(|_| vec![])).ok().unwrap();
+        let result = basis.rewrite(expr.as_ref());
+
+        // Only the operators of the basis are used
+        let allowed: &[&str] = match basis {
+            Basis::Nand => &["!&"],
+            Basis::Nor => &["!|"],
+            Basis::NotAnd => &["!", "&"],
+            Basis::NotImp => &["!", "=>"]
+        };
+        let operators = std::cell::RefCell::new(Vec::new());
+        result.traverse(&|e| {
+            if let Some(binary) = e.as_binary() {
+                let str = BinaryExpression::new(binary.op, Box::new(Value::new(false)), Box::new(Value::new(false))).to_string();
+                operators.borrow_mut().push(str[2..(str.len() - 2)].to_string());
+            } else if e.as_unary().is_some() {
+                operators.borrow_mut().push(String::from("!"));
+            }
+        });
+        for op in operators.borrow().iter() {
+            assert_eq!(allowed.contains(&op.as_str()), true, "{} is not allowed in {}", op, result.to_string());
+        }
+
+        // The result must be equivalent to the expression
+        assert_equivalent(expr.as_ref(), result.as_ref());
+        result.to_string()
+    }
+
+    #[test]
+    fn rewrite_into_nand() {
+        assert_eq!(rewritten(Basis::Nand, "!a"), "a !& a");
+        assert_eq!(rewritten(Basis::Nand, "a & b"), "(a !& b) !& (a !& b)");
+        assert_eq!(rewritten(Basis::Nand, "!(a & b)"), "a !& b");
+        assert_eq!(rewritten(Basis::Nand, "a => b"), "a !& (b !& b)");
+        assert_eq!(rewritten(Basis::Nand, "a & b | c & d"), "(a !& b) !& (c !& d)");
+    }
+
+    #[test]
+    fn rewrite_into_nor() {
+        assert_eq!(rewritten(Basis::Nor, "!a"), "a !| a");
+        assert_eq!(rewritten(Basis::Nor, "!a & !b"), "a !| b");
+        assert_eq!(rewritten(Basis::Nor, "(a | b) & (c | d)"), "(a !| b) !| (c !| d)");
+    }
+
+    #[test]
+    fn rewrite_into_not_and_not_imp() {
+        assert_eq!(rewritten(Basis::NotAnd, "a | b"), "!(!a & !b)");
+        assert_eq!(rewritten(Basis::NotAnd, "a => b"), "!(a & !b)");
+        assert_eq!(rewritten(Basis::NotImp, "a | b"), "!a => b");
+        assert_eq!(rewritten(Basis::NotImp, "a & b"), "!(a => !b)");
+        assert_eq!(rewritten(Basis::NotImp, "!a | b"), "a => b");
+    }
+
+    #[test]
+    fn rewrite_handles_all_operators() {
+        for basis in [Basis::Nand, Basis::Nor, Basis::NotAnd, Basis::NotImp] {
+            for str in ["a ^ b", "a = b", "a !^ b", "a <= b", "a !=> b", "s ? a : b", "maj(a, b, c)", "parity(a, b, c)",
+                "exists x. x & a | b", "forall x. x ^ a", "1 & a | 0"] {
+                rewritten(basis, str);
+            }
+        }
+        assert_eq!(rewritten(Basis::Nand, "a | !a"), "1");
+    }
+}
@@ -0,0 +1,322 @@
+use std::collections::BTreeMap;
+
+use crate::expression::{BinaryOperator, CardinalityOperator, Expression, UnaryOperator};
+
+/*
+ * Cnf
+ */
+
+/// A formula in conjunctive normal form, using DIMACS conventions: variables are numbered from 1,
+/// a literal is a positive or negative variable number.
+pub struct Cnf {
+    /// The names of the named variables, variable `i` is named `names[i - 1]`; all variables with a
+    /// higher number are auxiliary variables introduced by the encoding.
+    pub names: Vec<String>,
+    pub variable_count: usize,
+    pub clauses: Vec<Vec<i64>>,
+    numbers: BTreeMap<String, usize>,
+    true_literal: Option<i64>,
+}
+
+impl Cnf {
+    /// Creates an empty CNF, whereas the given variables get the numbers 1, 2, ...
+    pub fn new(names: Vec<String>) -> Cnf {
+        let numbers = names.iter().enumerate().map(|(i, name)| (name.clone(), i + 1)).collect();
+        Cnf {
+            variable_count: names.len(),
+            names,
+            clauses: Vec::new(),
+            numbers,
+            true_literal: None,
+        }
+    }
+
+    pub fn number(&self, name: &str) -> Option<usize> {
+        self.numbers.get(name).copied()
+    }
+
+    /// Adds clauses that force the given (quantifier free) expression to be true. Subexpressions
+    /// are encoded via Tseitin's transformation, so auxiliary variables might be introduced.
+    pub fn add_expression(&mut self, expr: &dyn Expression) -> Result<(), String> {
+        if let Some(binary) = expr.as_binary() {
+            if binary.op == BinaryOperator::AND {
+                self.add_expression(binary.left.as_ref())?;
+                return self.add_expression(binary.right.as_ref());
+            }
+        }
+
+        let mut literals = Vec::new();
+        if self.collect_disjunction(expr, &mut literals) {
+            if !literals.contains(&0) {
+                self.clauses.push(literals);
+            }
+            return Ok(());
+        }
+
+        let literal = self.encode(expr)?;
+        self.clauses.push(vec![literal]);
+        Ok(())
+    }
+
+    /// Collects the literals if the expression is a disjunction of literals; a `0` represents `true`.
+    fn collect_disjunction(&mut self, expr: &dyn Expression, literals: &mut Vec<i64>) -> bool {
+        if let Some(binary) = expr.as_binary() {
+            return binary.op == BinaryOperator::OR
+                && self.collect_disjunction(binary.left.as_ref(), literals)
+                && self.collect_disjunction(binary.right.as_ref(), literals);
+        }
+        if let Some(value) = expr.as_value() {
+            if value.value {
+                literals.push(0);
+            }
+            return true;
+        }
+        let (arg, negated) = match expr.as_unary() {
+            Some(unary) => (unary.arg.as_ref(), true),
+            None => (expr, false)
+        };
+        match arg.as_variable().and_then(|var| self.number(&var.name)) {
+            Some(number) => {
+                literals.push(if negated { -(number as i64) } else { number as i64 });
+                true
+            }
+            None => false
+        }
+    }
+
+    /// Returns a literal that is equivalent to the given expression.
+    pub fn encode(&mut self, expr: &dyn Expression) -> Result<i64, String> {
+        if let Some(value) = expr.as_value() {
+            let literal = self.true_literal();
+            return Ok(if value.value { literal } else { -literal });
+        }
+        if let Some(var) = expr.as_variable() {
+            return match self.number(&var.name) {
+                Some(number) => Ok(number as i64),
+                None => Err(format!("unknown variable '{}'", var.name))
+            };
+        }
+        if let Some(unary) = expr.as_unary() {
+            return match unary.op {
+                UnaryOperator::NEG => Ok(-self.encode(unary.arg.as_ref())?)
+            };
+        }
+        if let Some(binary) = expr.as_binary() {
+            let left = self.encode(binary.left.as_ref())?;
+            let right = self.encode(binary.right.as_ref())?;
+            return Ok(match binary.op {
+                BinaryOperator::AND => self.encode_and(&[left, right]),
+                BinaryOperator::OR => -self.encode_and(&[-left, -right]),
+                BinaryOperator::NAND => -self.encode_and(&[left, right]),
+                BinaryOperator::NOR => self.encode_and(&[-left, -right]),
+                BinaryOperator::IMP => -self.encode_and(&[left, -right]),
+                BinaryOperator::RIMP => -self.encode_and(&[-left, right]),
+                BinaryOperator::NIMP => self.encode_and(&[left, -right]),
+                BinaryOperator::XOR => self.encode_xor(left, right),
+                BinaryOperator::EQ | BinaryOperator::XNOR => -self.encode_xor(left, right),
+            });
+        }
+        if let Some(conditional) = expr.as_conditional() {
+            let condition = self.encode(conditional.condition.as_ref())?;
+            let if_true = self.encode(conditional.if_true.as_ref())?;
+            let if_false = self.encode(conditional.if_false.as_ref())?;
+            let result = self.new_variable();
+            self.clauses.push(vec![-result, -condition, if_true]);
+            self.clauses.push(vec![-result, condition, if_false]);
+            self.clauses.push(vec![result, -condition, -if_true]);
+            self.clauses.push(vec![result, condition, -if_false]);
+            return Ok(result);
+        }
+        if let Some(cardinality) = expr.as_cardinality() {
+            let mut args = Vec::new();
+            for arg in &cardinality.args {
+                args.push(self.encode(arg.as_ref())?);
+            }
+            if cardinality.op == CardinalityOperator::PARITY {
+                let Some((first, rest)) = args.split_first() else {
+                    return Ok(-self.true_literal());
+                };
+                let mut result = *first;
+                for arg in rest {
+                    result = self.encode_xor(result, *arg);
+                }
+                return Ok(result);
+            }
+
+            let counter = self.encode_totalizer(&args);
+            let true_literal = self.true_literal();
+            let at_least = |k: usize| match k {
+                0 => true_literal,
+                _ if k > counter.len() => -true_literal,
+                _ => counter[k - 1]
+            };
+            return Ok(match cardinality.op {
+                CardinalityOperator::MAJ => at_least(args.len() / 2 + 1),
+                CardinalityOperator::ATLEAST => at_least(cardinality.k),
+                CardinalityOperator::ATMOST => -at_least(cardinality.k + 1),
+                _ => {
+                    let (lower, upper) = (at_least(cardinality.k), -at_least(cardinality.k + 1));
+                    self.encode_and(&[lower, upper])
+                }
+            });
+        }
+        Err(format!("cannot convert '{}' to CNF", expr.to_string()))
+    }
+
+    fn new_variable(&mut self) -> i64 {
+        self.variable_count += 1;
+        self.variable_count as i64
+    }
+
+    fn true_literal(&mut self) -> i64 {
+        match self.true_literal {
+            Some(literal) => literal,
+            None => {
+                let literal = self.new_variable();
+                self.clauses.push(vec![literal]);
+                self.true_literal = Some(literal);
+                literal
+            }
+        }
+    }
+
+    fn encode_and(&mut self, args: &[i64]) -> i64 {
+        let result = self.new_variable();
+        let mut clause = vec![result];
+        for arg in args {
+            self.clauses.push(vec![-result, *arg]);
+            clause.push(-arg);
+        }
+        self.clauses.push(clause);
+        result
+    }
+
+    fn encode_xor(&mut self, left: i64, right: i64) -> i64 {
+        let result = self.new_variable();
+        self.clauses.push(vec![-result, left, right]);
+        self.clauses.push(vec![-result, -left, -right]);
+        self.clauses.push(vec![result, -left, right]);
+        self.clauses.push(vec![result, left, -right]);
+        result
+    }
+
+    /// Encodes a totalizer for the given literals, the `i`-th returned literal is true iff at
+    /// least `i + 1` of the given literals are true.
+    fn encode_totalizer(&mut self, args: &[i64]) -> Vec<i64> {
+        if args.len() <= 1 {
+            return args.to_vec();
+        }
+        let left = self.encode_totalizer(&args[..(args.len() / 2)]);
+        let right = self.encode_totalizer(&args[(args.len() / 2)..]);
+        let result: Vec<i64> = (0..args.len()).map(|_| self.new_variable()).collect();
+
+        // `a` and `b` are 1-based counts here, a count of zero is trivially reached
+        for a in 0..=left.len() {
+            for b in 0..=right.len() {
+                if a + b > 0 {
+                    let mut clause = vec![result[a + b - 1]];
+                    if a > 0 {
+                        clause.push(-left[a - 1]);
+                    }
+                    if b > 0 {
+                        clause.push(-right[b - 1]);
+                    }
+                    self.clauses.push(clause);
+                }
+                if a + b < args.len() {
+                    let mut clause = vec![-result[a + b]];
+                    if a < left.len() {
+                        clause.push(left[a]);
+                    }
+                    if b < right.len() {
+                        clause.push(right[b]);
+                    }
+                    self.clauses.push(clause);
+                }
+            }
+        }
+        result
+    }
+}
+
+
+/*
+ * Tests
+ */
+
+#[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+    use crate::expression::{CardinalityExpression, EvaluationContext, free_variables};
+    use crate::parser::parse;
+    use crate::tokens::tokenize;
+
+    fn cnf_of(str: &str) -> (Box<dyn Expression>, Cnf) {
+        let expr = parse(&tokenize(str).unwrap_or_else(|_| vec![])).ok().unwrap();
+        let mut cnf = Cnf::new(free_variables(expr.as_ref()).into_iter().collect());
+        cnf.add_expression(expr.as_ref()).unwrap();
+        (expr, cnf)
+    }
+
+    /// Checks by brute force whether the CNF is satisfiable with the named variables set as given.
+    fn satisfiable(cnf: &Cnf, named: u64) -> bool {
+        let aux = cnf.variable_count - cnf.names.len();
+        (0..(1u64 << aux)).any(|a| {
+            let values = named | (a << cnf.names.len());
+            cnf.clauses.iter().all(|clause| clause.iter().any(|literal| {
+                let value = values & (1 << (literal.abs() - 1)) != 0;
+                if *literal > 0 { value } else { !value }
+            }))
+        })
+    }
+
+    fn check_equisatisfiable(str: &str) {
+        let (expr, cnf) = cnf_of(str);
+        for named in 0..(1u64 << cnf.names.len()) {
+            let mut ctxt = crate::expression::EvaluationContext::new(cnf.names.iter().cloned().collect());
+            for (i, name) in cnf.names.iter().enumerate() {
+                ctxt.preset(name, named & (1 << i) != 0).unwrap();
+            }
+            assert_eq!(satisfiable(&cnf, named), expr.eval(&ctxt), "{} for {:b}", str, named);
+        }
+    }
+
+    #[test]
+    fn add_expression_creates_plain_clauses_for_conjunctions_of_disjunctions() {
+        let (_, cnf) = cnf_of("(a | !b) & c & (b | 0 | c) & (a | 1)");
+        assert_eq!(cnf.variable_count, 3);
+        assert_eq!(cnf.clauses, vec![vec![1, -2], vec![3], vec![2, 3]]);
+    }
+
+    #[test]
+    fn add_expression_is_equisatisfiable_for_operators() {
+        check_equisatisfiable("a = !b");
+        check_equisatisfiable("(a !& b) ^ (a !| c)");
+        check_equisatisfiable("(a => b) & (b <= c) | (a !=> c) | (a !^ b)");
+        check_equisatisfiable("a ? b : !c");
+        check_equisatisfiable("a & 1 | b & 0");
+    }
+
+    #[test]
+    fn add_expression_is_equisatisfiable_for_cardinality_functions() {
+        check_equisatisfiable("maj(a, b, c, d)");
+        check_equisatisfiable("!exactly(2; a, b, c, d)");
+        check_equisatisfiable("atmost(1; a, b, c) & atleast(1; a, d)");
+        check_equisatisfiable("!atleast(0; a, b) | atmost(5; a, b) & !atleast(3; a, b)");
+        check_equisatisfiable("parity(a, b, c) = d");
+    }
+
+    #[test]
+    fn add_expression_decides_cardinality_functions_without_arguments() {
+        let ctxt = EvaluationContext::new(BTreeSet::new());
+        for (op, k) in [(CardinalityOperator::PARITY, 0), (CardinalityOperator::MAJ, 0), (CardinalityOperator::ATLEAST, 0),
+            (CardinalityOperator::ATLEAST, 1), (CardinalityOperator::ATMOST, 0), (CardinalityOperator::EXACTLY, 0), (CardinalityOperator::EXACTLY, 1)] {
+            let expr = CardinalityExpression::new(op, k, vec![]);
+            let mut cnf = Cnf::new(vec![]);
+            cnf.add_expression(&expr).unwrap();
+            assert_eq!(satisfiable(&cnf, 0), expr.eval(&ctxt), "{}", expr.to_string());
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::anf::truth_table;
+use crate::expression::{BinaryExpression, BinaryOperator, Expression, free_variables, UnaryExpression, UnaryOperator, Variable};
+use crate::simplify::{residual, simplify};
+
+/*
+ * Cofactors
+ */
+
+/// Returns the cofactor `f|var=value`, i.e. the simplified expression with the variable fixed.
+pub fn cofactor(expr: &dyn Expression, var: &str, value: bool) -> Box<dyn Expression> {
+    let mut presets = BTreeMap::new();
+    presets.insert(var.to_string(), value);
+    residual(expr, &presets)
+}
+
+/// Returns the Boolean difference `∂f/∂var = f|var=0 ^ f|var=1`, which is true for the values of
+/// the other variables for which a change of the variable changes the value of the expression.
+pub fn boolean_difference(expr: &dyn Expression, var: &str) -> Box<dyn Expression> {
+    simplify(&BinaryExpression::new(BinaryOperator::XOR, cofactor(expr, var, false), cofactor(expr, var, true)))
+}
+
+/// Returns the Shannon expansion `var & f|var=1 | !var & f|var=0` by the variables in the given
+/// order; the cofactors are expanded by the next variables. A variable that does not occur (any
+/// more) is skipped, so repeated variables are expanded only once.
+pub fn shannon_expansion(expr: &dyn Expression, vars: &[String]) -> Box<dyn Expression> {
+    let Some((var, rest)) = vars.split_first() else {
+        return simplify(expr);
+    };
+    if !free_variables(expr).contains(var) {
+        return shannon_expansion(expr, rest);
+    }
+    let if_true = shannon_expansion(cofactor(expr, var, true).as_ref(), rest);
+    let if_false = shannon_expansion(cofactor(expr, var, false).as_ref(), rest);
+    let positive = BinaryExpression::new(BinaryOperator::AND, Box::new(Variable::new(var)), if_true);
+    let negative = BinaryExpression::new(BinaryOperator::AND, Box::new(UnaryExpression::new(UnaryOperator::NEG, Box::new(Variable::new(var)))), if_false);
+    simplify(&BinaryExpression::new(BinaryOperator::OR, Box::new(positive), Box::new(negative)))
+}
+
+/// Returns the variables the expression actually depends on, i.e. those with a Boolean
+/// difference that is not always `0`; the others can be dropped without changing the function.
+pub fn essential_variables(expr: &dyn Expression) -> Result<BTreeSet<String>, String> {
+    let (variables, table) = truth_table(expr)?;
+    Ok(variables.into_iter().enumerate()
+        .filter(|(bit, _)| (0..table.len()).any(|i| table[i] != table[i ^ (1 << bit)]))
+        .map(|(_, var)| var)
+        .collect())
+}
+
+/*
+ * Tests
+ */
+
+#[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+    use crate::tokens::tokenize;
+
+    fn parsed(str: &str) -> Box<dyn Expression> {
+        parse(&tokenize(str).unwrap_or_else(|_| vec![])).ok().unwrap()
+    }
+
+    #[test]
+    fn cofactor_fixes_the_variable() {
+        let expr = parsed("s ? a : b");
+        assert_eq!(cofactor(expr.as_ref(), "s", true).to_string(), "a");
+        assert_eq!(cofactor(expr.as_ref(), "s", false).to_string(), "b");
+        assert_eq!(cofactor(expr.as_ref(), "a", false).to_string(), "!s & b");
+        assert_eq!(cofactor(expr.as_ref(), "x", false).to_string(), "s ? a : b");
+    }
+
+    #[test]
+    fn boolean_difference_tells_when_the_variable_matters() {
+        assert_eq!(boolean_difference(parsed("a & b").as_ref(), "a").to_string(), "b");
+        assert_eq!(boolean_difference(parsed("a | b").as_ref(), "a").to_string(), "!b");
+        assert_eq!(boolean_difference(parsed("a ^ b").as_ref(), "a").to_string(), "1");
+        assert_eq!(boolean_difference(parsed("a ^ b").as_ref(), "c").to_string(), "0");
+        assert_eq!(boolean_difference(parsed("s ? a : b").as_ref(), "s").to_string(), "b ^ a");
+    }
+
+    #[test]
+    fn essential_variables_ignores_redundant_variables() {
+        let essential = |str: &str| essential_variables(parsed(str).as_ref()).ok().unwrap().into_iter().collect::<Vec<_>>();
+        assert_eq!(essential("a | (a & b)"), vec!["a"]);
+        assert_eq!(essential("(a ^ b) ^ b | c & !c"), vec!["a"]);
+        assert_eq!(essential("s ? a : b"), vec!["a", "b", "s"]);
+        assert_eq!(essential("exists x. x & a | b"), vec!["a", "b"]);
+        assert_eq!(essential("a | !a"), Vec::<String>::new());
+
+        let many = (0..30).map(|i| format!("v{}", i)).collect::<Vec<_>>().join(" | ");
+        assert_eq!(essential_variables(parsed(&many).as_ref()).err().unwrap(), "the expression has more than 24 variables");
+    }
+
+    #[test]
+    fn shannon_expansion_expands_by_all_variables() {
+        let expr = parsed("a ^ b");
+        assert_eq!(shannon_expansion(expr.as_ref(), &[String::from("a")]).to_string(), "a & !b | !a & b");
+        assert_eq!(shannon_expansion(expr.as_ref(), &[String::from("a"), String::from("b")]).to_string(), "a & !b | !a & b");
+        assert_eq!(shannon_expansion(parsed("a | b").as_ref(), &[String::from("a")]).to_string(), "a | !a & b");
+        assert_eq!(shannon_expansion(expr.as_ref(), &[]).to_string(), "a ^ b");
+    }
+
+    #[test]
+    fn shannon_expansion_skips_repeated_and_absent_variables() {
+        let expr = parsed("a ^ b");
+        assert_eq!(shannon_expansion(expr.as_ref(), &[String::from("a"), String::from("a")]).to_string(), "a & !b | !a & b");
+        assert_eq!(shannon_expansion(expr.as_ref(), &[String::from("z")]).to_string(), "a ^ b");
+        assert_eq!(shannon_expansion(parsed("a | b").as_ref(), &[String::from("a"), String::from("b")]).to_string(), "a | !a & b");
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::BTreeSet;
+
+use crate::anf::{Anf, truth_table};
+use crate::diagnostic::display_width;
+use crate::expression::Expression;
+
+/*
+ * Properties
+ */
+
+/// The properties of a Boolean function that matter in cryptography.
+pub struct Properties {
+    pub variables: Vec<String>,
+    /// The Walsh–Hadamard spectrum `W(w) = Σ (-1)^(f(x) ^ w·x)`, indexed like the truth table
+    pub walsh: Vec<i64>,
+    /// The distance to the nearest affine function
+    pub nonlinearity: u64,
+    /// Whether the function is `1` for exactly half of the assignments
+    pub balanced: bool,
+    /// The largest `m` such that the value is independent of any `m` variables
+    pub correlation_immunity: usize,
+    /// The correlation immunity of a balanced function, `None` otherwise
+    pub resiliency: Option<usize>,
+    pub degree: usize,
+    /// For each variable the probability that flipping it changes the value
+    pub avalanche: Vec<f64>,
+    /// Whether all avalanche probabilities are exactly 1/2 (strict avalanche criterion)
+    pub sac: bool,
+}
+
+impl Properties {
+    pub fn from_expression(expr: &dyn Expression) -> Result<Properties, String> {
+        let (variables, table) = truth_table(expr)?;
+        let degree = Anf::with_polarity(expr, &BTreeSet::new())?.degree();
+        let walsh = walsh_spectrum(&table);
+        let size = table.len() as u64;
+
+        let max = walsh.iter().map(|value| value.unsigned_abs()).max().unwrap_or(0);
+        let balanced = walsh[0] == 0;
+        let correlation_immunity = (1..=variables.len())
+            .take_while(|order| walsh.iter().enumerate().all(|(w, value)| w.count_ones() as usize != *order || *value == 0))
+            .count();
+        let avalanche: Vec<f64> = (0..variables.len()).map(|bit| {
+            let changes = (0..table.len()).filter(|i| table[*i] != table[i ^ (1 << bit)]).count();
+            changes as f64 / table.len() as f64
+        }).collect();
+        let sac = avalanche.iter().all(|probability| *probability == 0.5);
+
+        Ok(Properties {
+            variables,
+            walsh,
+            nonlinearity: (size - max) / 2,
+            balanced,
+            correlation_immunity,
+            resiliency: if balanced { Some(correlation_immunity) } else { None },
+            degree,
+            avalanche,
+            sac,
+        })
+    }
+
+    /// Returns the properties as lines of `name: value`, followed by the Walsh spectrum in a table
+    /// like the truth table.
+    pub fn to_table(&self) -> String {
+        let yes_no = |value: bool| if value { "yes" } else { "no" };
+        let avalanche: Vec<String> = self.variables.iter().zip(&self.avalanche).map(|(var, probability)| format!("{}: {}", var, probability)).collect();
+        let mut result = String::new();
+        result.push_str(&format!("variables:            {}\n", self.variables.join(", ")));
+        result.push_str(&format!("balanced:             {}\n", yes_no(self.balanced)));
+        result.push_str(&format!("nonlinearity:         {}\n", self.nonlinearity));
+        result.push_str(&format!("algebraic degree:     {}\n", self.degree));
+        result.push_str(&format!("correlation immunity: {}\n", self.correlation_immunity));
+        result.push_str(&format!("resiliency:           {}\n", self.resiliency.map_or(String::from("-"), |order| order.to_string())));
+        result.push_str(&format!("avalanche:            {}\n", avalanche.join(", ")));
+        result.push_str(&format!("SAC:                  {}\n", yes_no(self.sac)));
+
+        // Without variables there are no columns to separate the spectrum from
+        let width = self.walsh.iter().map(|value| value.to_string().len()).max().unwrap_or(1);
+        let (bar, cross) = if self.variables.is_empty() { ("", "") } else { ("|", "+") };
+        result.push_str("\n|");
+        for var in &self.variables {
+            result.push_str(&format!(" {} |", var));
+        }
+        result.push_str(&format!("{} {:>width$} |\n+", bar, "W", width = width));
+        for var in &self.variables {
+            result.push_str(&format!("{}+", "-".repeat(display_width(var) + 2)));
+        }
+        result.push_str(&format!("{}{}+\n", cross, "-".repeat(width + 2)));
+        for (w, value) in self.walsh.iter().enumerate() {
+            result.push('|');
+            for (bit, var) in self.variables.iter().enumerate() {
+                result.push_str(&format!("{}{} |", " ".repeat(display_width(var)), (w >> bit) & 1));
+            }
+            result.push_str(&format!("{} {:>width$} |\n", bar, value, width = width));
+        }
+        result
+    }
+
+    pub fn to_json(&self) -> String {
+        let list = |items: Vec<String>| format!("[{}]", items.join(", "));
+        let fields = [
+            ("variables", list(self.variables.iter().map(|var| json_string(var)).collect())),
+            ("walsh", list(self.walsh.iter().map(|value| value.to_string()).collect())),
+            ("nonlinearity", self.nonlinearity.to_string()),
+            ("balanced", self.balanced.to_string()),
+            ("algebraic_degree", self.degree.to_string()),
+            ("correlation_immunity", self.correlation_immunity.to_string()),
+            ("resiliency", self.resiliency.map_or(String::from("null"), |order| order.to_string())),
+            ("avalanche", list(self.avalanche.iter().map(|probability| probability.to_string()).collect())),
+            ("sac", self.sac.to_string()),
+        ];
+        let fields: Vec<String> = fields.iter().map(|(name, value)| format!("  \"{}\": {}", name, value)).collect();
+        format!("{{\n{}\n}}\n", fields.join(",\n"))
+    }
+}
+
+/// Computes the Walsh–Hadamard spectrum of the truth table by the fast transform.
+fn walsh_spectrum(table: &[bool]) -> Vec<i64> {
+    let mut spectrum: Vec<i64> = table.iter().map(|value| if *value { -1 } else { 1 }).collect();
+    let mut step = 1;
+    while step < spectrum.len() {
+        for i in 0..spectrum.len() {
+            if i & step == 0 {
+                let (a, b) = (spectrum[i], spectrum[i | step]);
+                spectrum[i] = a + b;
+                spectrum[i | step] = a - b;
+            }
+        }
+        step <<= 1;
+    }
+    spectrum
+}
+
+fn json_string(str: &str) -> String {
+    let mut result = String::from("\"");
+    for ch in str.chars() {
+        match ch {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            _ if (ch as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", ch as u32)),
+            _ => result.push(ch)
+        }
+    }
+    result.push('"');
+    result
+}
+
+/*
+ * Tests
+ */
+
+#[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+    use crate::tokens::tokenize;
+
+    fn properties(str: &str) -> Properties {
+        let expr = parse(&tokenize(str).unwrap_or_else(|_| vec![])).ok().unwrap();
+        Properties::from_expression(expr.as_ref()).ok().unwrap()
+    }
+
+    #[test]
+    fn walsh_spectrum_of_small_functions() {
+        assert_eq!(properties("a & b").walsh, vec![2, 2, 2, -2]);
+        assert_eq!(properties("a ^ b").walsh, vec![0, 0, 0, 4]);
+        assert_eq!(properties("1").walsh, vec![-1]);
+    }
+
+    #[test]
+    fn properties_of_linear_and_bent_functions() {
+        let linear = properties("a ^ b ^ c");
+        assert_eq!(linear.balanced, true);
+        assert_eq!(linear.nonlinearity, 0);
+        assert_eq!(linear.degree, 1);
+        assert_eq!(linear.correlation_immunity, 2);
+        assert_eq!(linear.resiliency, Some(2));
+        assert_eq!(linear.avalanche, vec![1.0, 1.0, 1.0]);
+        assert_eq!(linear.sac, false);
+
+        let bent = properties("a & b ^ c & d");
+        assert_eq!(bent.balanced, false);
+        assert_eq!(bent.nonlinearity, 6);
+        assert_eq!(bent.degree, 2);
+        assert_eq!(bent.correlation_immunity, 0);
+        assert_eq!(bent.resiliency, None);
+        assert_eq!(bent.sac, true);
+    }
+
+    #[test]
+    fn properties_of_the_majority() {
+        let maj = properties("maj(a, b, c)");
+        assert_eq!(maj.balanced, true);
+        assert_eq!(maj.nonlinearity, 2);
+        assert_eq!(maj.correlation_immunity, 0);
+        assert_eq!(maj.resiliency, Some(0));
+        assert_eq!(maj.avalanche, vec![0.5, 0.5, 0.5]);
+        assert_eq!(maj.sac, true);
+    }
+
+    #[test]
+    fn to_table_and_to_json() {
+        let xor = properties("a ^ b");
+        assert_eq!(xor.to_table(), "variables:            a, b\nbalanced:             yes\nnonlinearity:         0\n\
+            algebraic degree:     1\ncorrelation immunity: 1\nresiliency:           1\navalanche:            a: 1, b: 1\nSAC:                  no\n\n\
+            | a | b || W |\n+---+---++---+\n| 0 | 0 || 0 |\n| 1 | 0 || 0 |\n| 0 | 1 || 0 |\n| 1 | 1 || 4 |\n");
+        assert_eq!(xor.to_json(), "{\n  \"variables\": [\"a\", \"b\"],\n  \"walsh\": [0, 0, 0, 4],\n  \"nonlinearity\": 0,\n  \"balanced\": true,\n  \
+            \"algebraic_degree\": 1,\n  \"correlation_immunity\": 1,\n  \"resiliency\": 1,\n  \"avalanche\": [1, 1],\n  \"sac\": false\n}\n");
+        assert_eq!(json_string("a\"b\\c"), "\"a\\\"b\\\\c\"");
+    }
+
+    #[test]
+    fn to_table_of_a_constant_has_no_variable_columns() {
+        assert_eq!(properties("1").to_table(), "variables:            \nbalanced:             no\nnonlinearity:         0\n\
+            algebraic degree:     0\ncorrelation immunity: 0\nresiliency:           -\navalanche:            \nSAC:                  yes\n\n\
+            |  W |\n+----+\n| -1 |\n");
+    }
+}
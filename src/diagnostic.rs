@@ -0,0 +1,173 @@
+use crate::tokens::{Diagnostic, line_column, Location};
+
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// Renders a diagnostic similar to rustc: a header with the error code, the location and the
+/// offending source lines with the erroneous part underlined by `~` and labelled by the message;
+/// related parts are underlined by `-`, a help text is appended.
+pub fn render_diagnostic(name: &str, source: &str, diagnostic: &Diagnostic, colour: bool) -> String {
+    let paint = |text: &str, style: &str| if colour { format!("{}{}{}", style, text, RESET) } else { String::from(text) };
+    let err = &diagnostic.error;
+    let start = err.span(source).start;
+
+    // Annotations as (start, end, message, primary)
+    let mut annotations = vec![(start, err.span(source).end, err.message.as_str(), true)];
+    for label in &diagnostic.labels {
+        let (line, column) = line_column(source, label.pos);
+        let (end_line, end_column) = line_column(source, label.pos + label.len);
+        annotations.push((Location { line, column }, Location { line: end_line, column: end_column }, label.message.as_str(), false));
+    }
+    let mut lines: Vec<usize> = annotations.iter().map(|(start, _, _, _)| start.line).collect();
+    lines.sort();
+    lines.dedup();
+
+    let gutter = " ".repeat(lines[lines.len() - 1].to_string().len());
+    let mut result = String::new();
+    let (severity, style) = if err.code.is_warning() { ("warning", YELLOW) } else { ("error", RED) };
+    result += &format!("{}{}\n", paint(&format!("{}[{}]", severity, err.code.as_str()), style), paint(&format!(": {}", err.message), BOLD));
+    result += &format!("{}{} {}:{}:{}\n", gutter, paint("-->", BLUE), name, start.line, start.column);
+    result += &format!("{} {}\n", gutter, paint("|", BLUE));
+    for number in lines {
+        let line = source.split('\n').nth(number - 1).unwrap_or("").trim_end_matches('\r').replace('\t', " ");
+        let chars: Vec<char> = line.chars().collect();
+        // The number is padded before it is painted, escape codes take no columns
+        result += &format!("{} {} {}\n", paint(&format!("{:>width$}", number, width = gutter.len()), BLUE), paint("|", BLUE), line);
+        for (start, end, message, primary) in annotations.iter().filter(|(start, _, _, _)| start.line == number) {
+            // Annotations spanning several lines are underlined up to the end of the first line
+            let from = chars.len().min(start.column - 1);
+            let to = if end.line == start.line { chars.len().min(end.column - 1) } else { chars.len() };
+            let offset = display_width(&chars[..from].iter().collect::<String>());
+            let len = display_width(&chars[from..to].iter().collect::<String>()).max(1);
+            let (underline, style) = if !*primary { ("-", BLUE) } else if err.code.is_warning() { ("~", YELLOW) } else { ("~", RED) };
+            result += &format!("{} {} {}{}\n", gutter, paint("|", BLUE), " ".repeat(offset), paint(&format!("{} {}", underline.repeat(len), message), style));
+        }
+    }
+    if let Some(help) = &diagnostic.help {
+        result += &format!("{} {} {}\n", gutter, paint("=", BLUE), paint(&format!("help: {}", help), BOLD));
+    }
+    result
+}
+
+/// Returns the number of terminal columns the given string occupies.
+pub fn display_width(str: &str) -> usize {
+    str.chars().map(char_width).sum()
+}
+
+fn char_width(ch: char) -> usize {
+    match ch as u32 {
+        // Combining marks and zero width characters
+        0x0300..=0x036f | 0x1ab0..=0x1aff | 0x1dc0..=0x1dff | 0x200b..=0x200f | 0x20d0..=0x20ff | 0xfe20..=0xfe2f => 0,
+        // East asian wide and fullwidth characters, emojis
+        0x1100..=0x115f | 0x2e80..=0x303e | 0x3041..=0x33ff | 0x3400..=0x4dbf | 0x4e00..=0x9fff | 0xa000..=0xa4cf
+        | 0xac00..=0xd7a3 | 0xf900..=0xfaff | 0xfe30..=0xfe4f | 0xff00..=0xff60 | 0xffe0..=0xffe6
+        | 0x1f300..=0x1f64f | 0x1f900..=0x1f9ff | 0x20000..=0x3fffd => 2,
+        _ => 1
+    }
+}
+
+/*
+ * Tests
+ */
+
+#[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_all, parse_with_options, ParseOptions};
+    use crate::tokens::tokenize;
+
+    #[test]
+    fn render_diagnostic_underlines_the_error() {
+        let source = "a &\n  (b | $)";
+        let err = tokenize(source).err().unwrap();
+        assert_eq!(render_diagnostic("<expr>", source, &err.into(), false),
+                   "error[E0001]: Invalid character '$'\n \
+                    --> <expr>:2:8\n  \
+                    |\n\
+                    2 |   (b | $)\n  \
+                    |        ~ Invalid character '$'\n");
+    }
+
+    #[test]
+    fn render_diagnostic_uses_display_width_and_cuts_multi_line_spans() {
+        let source = "変数 & \"bus\n& c";
+        let err = tokenize(source).err().unwrap();
+        assert_eq!(render_diagnostic("f.txt", source, &err.into(), false),
+                   "error[E0002]: unterminated quoted name\n \
+                    --> f.txt:1:6\n  \
+                    |\n\
+                    1 | 変数 & \"bus\n  \
+                    |        ~~~~ unterminated quoted name\n");
+    }
+
+    #[test]
+    fn render_diagnostic_colours_on_request() {
+        let source = "$";
+        let err = tokenize(source).err().unwrap();
+        let result = render_diagnostic("<expr>", source, &err.into(), true);
+        assert_eq!(result.starts_with("\x1b[1;31merror[E0001]\x1b[0m"), true);
+        assert_eq!(result.contains("\x1b[1;31m~ Invalid character '$'\x1b[0m"), true);
+
+        // Line numbers of different widths are aligned
+        let source = format!("{}(a &\n  b c", "\n".repeat(8));
+        let tokens = tokenize(&source).unwrap();
+        let errors = parse_all(&tokens).err().unwrap();
+        let result = render_diagnostic("<expr>", &source, &errors[1], true);
+        assert_eq!(result.contains("\n\x1b[1;34m 9\x1b[0m \x1b[1;34m|\x1b[0m (a &\n"), true);
+        assert_eq!(result.contains("\n\x1b[1;34m10\x1b[0m \x1b[1;34m|\x1b[0m   b c\n"), true);
+    }
+
+    #[test]
+    fn render_diagnostic_shows_labels_and_help() {
+        let source = "(a &\n  b c";
+        let tokens = tokenize(source).unwrap();
+        let errors = parse_all(&tokens).err().unwrap();
+        assert_eq!(render_diagnostic("<expr>", source, &errors[1], false),
+                   "error[E0004]: \")\" expected\n \
+                    --> <expr>:2:6\n  \
+                    |\n\
+                    1 | (a &\n  \
+                    | - unbalanced `(` opened here\n\
+                    2 |   b c\n  \
+                    |      ~ \")\" expected\n");
+
+        let source = "a and b";
+        let tokens = tokenize(source).unwrap();
+        let errors = parse_all(&tokens).err().unwrap();
+        assert_eq!(render_diagnostic("<expr>", source, &errors[0], false),
+                   "error[E0004]: operator expected\n \
+                    --> <expr>:1:3\n  \
+                    |\n\
+                    1 | a and b\n  \
+                    |   ~~~ operator expected\n  \
+                    = help: did you mean `&`?\n");
+    }
+
+    #[test]
+    fn render_diagnostic_shows_warnings() {
+        let source = "a => b => c";
+        let tokens = tokenize(source).unwrap();
+        let mut warnings = Vec::new();
+        parse_with_options(&tokens, &ParseOptions::default(), &mut warnings).unwrap();
+        assert_eq!(render_diagnostic("<expr>", source, &warnings[0], false),
+                   "warning[W0001]: ambiguous chain of `=>` and `=>`\n \
+                    --> <expr>:1:8\n  \
+                    |\n\
+                    1 | a => b => c\n  \
+                    |        ~~ ambiguous chain of `=>` and `=>`\n  \
+                    |   -- chained with this operator\n  \
+                    = help: this is read as `a => (b => c)`, add parentheses to make this explicit\n");
+        let result = render_diagnostic("<expr>", source, &warnings[0], true);
+        assert_eq!(result.starts_with("\x1b[1;33mwarning[W0001]\x1b[0m"), true);
+    }
+
+    #[test]
+    fn display_width_counts_wide_and_combining_characters() {
+        assert_eq!(display_width("a変b"), 4);
+        assert_eq!(display_width("e\u{0301}"), 1);
+    }
+}
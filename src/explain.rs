@@ -0,0 +1,460 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::expression::{BinaryExpression, BinaryOperator, CardinalityOperator, expand_cardinality, Expression, free_variables, Quantifier, replace_child, UnaryExpression, UnaryOperator, Value, Variable};
+use crate::diagnostic::display_width;
+use crate::simplify::{complementary, flatten, same};
+
+/// The maximum number of steps of an explanation, the distributive law might blow up an
+/// expression exponentially.
+const MAX_STEPS: usize = 2000;
+
+/// A step of an explanation: the law that was applied and the resulting expression.
+pub struct Step {
+    pub law: &'static str,
+    pub expr: Box<dyn Expression>,
+}
+
+/// A term of a disjunctive normal form, a list of literals each given by its variable and
+/// whether it is positive.
+type Term = Vec<(String, bool)>;
+
+/*
+ * Explanations
+ */
+
+/// Explains how the expression is rewritten into a disjunctive normal form: the operators are
+/// replaced by their definitions, negations are moved inwards by De Morgan's laws, conjunctions
+/// are distributed over disjunctions and finally the terms are ordered and reduced.
+pub fn explain_dnf(expr: &dyn Expression) -> Result<Vec<Step>, String> {
+    let mut steps = Vec::new();
+    let terms = rewrite_to_dnf(expr, &mut steps)?;
+    normalize(terms, &mut steps);
+    Ok(steps)
+}
+
+/// Explains why `left` is equivalent to `right`: both are rewritten into a disjunctive normal
+/// form and, if these differ, into their canonical disjunctive normal form; the steps for `right`
+/// are reversed to arrive at `right`.
+pub fn explain_equivalence(left: &dyn Expression, right: &dyn Expression) -> Result<Vec<Step>, String> {
+    let mut left_steps = Vec::new();
+    let left_terms = normalize(rewrite_to_dnf(left, &mut left_steps)?, &mut left_steps);
+    let mut right_steps = Vec::new();
+    let right_terms = normalize(rewrite_to_dnf(right, &mut right_steps)?, &mut right_steps);
+    if meeting_point(left, &left_steps, right, &right_steps).is_none() {
+        let mut variables = free_variables(left);
+        variables.extend(free_variables(right));
+        let left_form = to_expression(&expand(left_terms, &variables, &mut left_steps)?);
+        let right_form = to_expression(&expand(right_terms, &variables, &mut right_steps)?);
+        if left_form.to_string() != right_form.to_string() {
+            return Err(format!("the expressions are not equivalent, their canonical forms are `{}` and `{}`", left_form.to_string(), right_form.to_string()));
+        }
+    }
+
+    // The proof follows the steps for `left` to the first expression also reached from `right`
+    // and from there walks back
+    let (meet_left, meet_right) = meeting_point(left, &left_steps, right, &right_steps).unwrap();
+    left_steps.truncate(meet_left);
+    let mut previous: Vec<Box<dyn Expression>> = vec![right.clone_box()];
+    previous.extend(right_steps.iter().take(meet_right).map(|step| step.expr.clone_box()));
+    for step in right_steps.into_iter().take(meet_right).rev() {
+        previous.pop();
+        left_steps.push(Step { law: step.law, expr: previous.last().unwrap().clone_box() });
+    }
+    Ok(left_steps)
+}
+
+/// Returns the number of steps from `left` and from `right` to the first expression on the way
+/// from `left` that is also reached from `right`.
+fn meeting_point(left: &dyn Expression, left_steps: &[Step], right: &dyn Expression, right_steps: &[Step]) -> Option<(usize, usize)> {
+    let mut left_chain = vec![left.to_string()];
+    left_chain.extend(left_steps.iter().map(|step| step.expr.to_string()));
+    let mut right_chain = vec![right.to_string()];
+    right_chain.extend(right_steps.iter().map(|step| step.expr.to_string()));
+    left_chain.iter().enumerate()
+        .find_map(|(i, str)| right_chain.iter().position(|other| other == str).map(|j| (i, j)))
+}
+
+/// Formats the explanation of the expression, one step per line with the law at the end.
+pub fn format_steps(expr: &dyn Expression, steps: &[Step]) -> String {
+    let width = steps.iter().map(|step| display_width(&step.expr.to_string())).max().unwrap_or(0);
+    let mut result = format!("  {}\n", expr.to_string());
+    for step in steps {
+        let str = step.expr.to_string();
+        result.push_str(&format!("= {}{}   ({})\n", str, " ".repeat(width - display_width(&str)), step.law));
+    }
+    result
+}
+
+/*
+ * Rewriting
+ */
+
+/// Rewrites the expression into a disjunction of conjunctions of literals, recording the steps.
+fn rewrite_to_dnf(expr: &dyn Expression, steps: &mut Vec<Step>) -> Result<Vec<Term>, String> {
+    let mut current = expr.clone_box();
+    for rule in [definition as Rule, de_morgan, distributivity] {
+        while let Some((law, next)) = rewrite(current.as_ref(), rule) {
+            if steps.len() == MAX_STEPS {
+                return Err(format!("the explanation takes more than {} steps", MAX_STEPS));
+            }
+            steps.push(Step { law, expr: next.clone_box() });
+            current = next;
+        }
+    }
+    Ok(to_terms(current.as_ref()))
+}
+
+/// A rule rewrites an expression at its top level, returning the law applied and the result.
+type Rule = fn(&dyn Expression) -> Option<(&'static str, Box<dyn Expression>)>;
+
+/// Applies the first matching law to the outermost, leftmost possible subexpression. The laws
+/// of Boolean algebra that reduce the expression are tried before the rule.
+fn rewrite(expr: &dyn Expression, rule: Rule) -> Option<(&'static str, Box<dyn Expression>)> {
+    if let Some(result) = reduction(expr).or_else(|| rule(expr)) {
+        return Some(result);
+    }
+    let children = expr.children();
+    for (index, child) in children.iter().enumerate() {
+        if let Some((law, rewritten)) = rewrite(*child, rule) {
+            return Some((law, replace_child(expr, index, rewritten)));
+        }
+    }
+    None
+}
+
+/// The laws that make an expression smaller: constants, double negation, identity, annihilator,
+/// idempotence, complement and absorption.
+fn reduction(expr: &dyn Expression) -> Option<(&'static str, Box<dyn Expression>)> {
+    if let Some(unary) = expr.as_unary() {
+        if let Some(value) = unary.arg.as_value() {
+            return Some(("negation of a constant", value_of(!value.value)));
+        }
+        if let Some(inner) = unary.arg.as_unary() {
+            return Some(("double negation", inner.arg.clone_box()));
+        }
+        return None;
+    }
+    let binary = expr.as_binary().filter(|binary| matches!(binary.op, BinaryOperator::AND | BinaryOperator::OR))?;
+    let (left, right) = (binary.left.as_ref(), binary.right.as_ref());
+    let neutral = binary.op == BinaryOperator::AND;
+    let dual = if neutral { BinaryOperator::OR } else { BinaryOperator::AND };
+
+    for (constant, other) in [(left, right), (right, left)] {
+        if let Some(value) = constant.as_value() {
+            return if value.value == neutral {
+                Some(("identity", other.clone_box()))
+            } else {
+                Some(("annihilator", value_of(!neutral)))
+            };
+        }
+    }
+    if same(left, right) {
+        return Some(("idempotence", left.clone_box()));
+    }
+    if complementary(left, right) {
+        return Some(("complement", value_of(!neutral)));
+    }
+    for (absorbing, other) in [(left, right), (right, left)] {
+        let mut parts = Vec::new();
+        flatten(dual, other.clone_box(), &mut parts);
+        if parts.len() > 1 && parts.iter().any(|part| same(part.as_ref(), absorbing)) {
+            return Some(("absorption", absorbing.clone_box()));
+        }
+    }
+    None
+}
+
+/// Replaces an operator other than `!`, `&` and `|` by its definition.
+fn definition(expr: &dyn Expression) -> Option<(&'static str, Box<dyn Expression>)> {
+    if let Some(binary) = expr.as_binary() {
+        let (a, b) = (|| binary.left.clone_box(), || binary.right.clone_box());
+        return match binary.op {
+            BinaryOperator::AND | BinaryOperator::OR => None,
+            BinaryOperator::IMP => Some(("definition of =>", or(not(a()), b()))),
+            BinaryOperator::RIMP => Some(("definition of <=", or(a(), not(b())))),
+            BinaryOperator::NIMP => Some(("definition of !=>", and(a(), not(b())))),
+            BinaryOperator::NAND => Some(("definition of !&", not(and(a(), b())))),
+            BinaryOperator::NOR => Some(("definition of !|", not(or(a(), b())))),
+            BinaryOperator::EQ => Some(("definition of =", or(and(a(), b()), and(not(a()), not(b()))))),
+            BinaryOperator::XNOR => Some(("definition of !^", or(and(a(), b()), and(not(a()), not(b()))))),
+            BinaryOperator::XOR => Some(("definition of ^", or(and(a(), not(b())), and(not(a()), b()))))
+        };
+    }
+    if let Some(conditional) = expr.as_conditional() {
+        let condition = || conditional.condition.clone_box();
+        return Some(("definition of ? :", or(and(condition(), conditional.if_true.clone_box()), and(not(condition()), conditional.if_false.clone_box()))));
+    }
+    if let Some(cardinality) = expr.as_cardinality() {
+        let expanded = expand_cardinality(cardinality);
+        let law = match cardinality.op {
+            CardinalityOperator::MAJ => "definition of maj",
+            _ if expanded.as_value().is_some() => "definition of the counting function",
+            _ => "Shannon expansion"
+        };
+        return Some((law, expanded));
+    }
+    if let Some(quantified) = expr.as_quantified() {
+        let cofactor = |value: bool| {
+            let mut bindings: BTreeMap<String, Box<dyn Expression>> = BTreeMap::new();
+            bindings.insert(quantified.var.clone(), value_of(value));
+            quantified.body.substitute(&bindings)
+        };
+        return Some(match quantified.quantifier {
+            Quantifier::EXISTS => ("expansion of exists", or(cofactor(false), cofactor(true))),
+            Quantifier::FORALL => ("expansion of forall", and(cofactor(false), cofactor(true)))
+        });
+    }
+    None
+}
+
+/// Moves a negation inwards by De Morgan's laws.
+fn de_morgan(expr: &dyn Expression) -> Option<(&'static str, Box<dyn Expression>)> {
+    let binary = expr.as_unary()?.arg.as_binary()?;
+    let (a, b) = (not(binary.left.clone_box()), not(binary.right.clone_box()));
+    match binary.op {
+        BinaryOperator::AND => Some(("De Morgan", or(a, b))),
+        BinaryOperator::OR => Some(("De Morgan", and(a, b))),
+        _ => None
+    }
+}
+
+/// Distributes a conjunction over a disjunction.
+fn distributivity(expr: &dyn Expression) -> Option<(&'static str, Box<dyn Expression>)> {
+    let binary = expr.as_binary().filter(|binary| binary.op == BinaryOperator::AND)?;
+    if let Some(right) = binary.right.as_binary().filter(|right| right.op == BinaryOperator::OR) {
+        let a = || binary.left.clone_box();
+        return Some(("distributivity", or(and(a(), right.left.clone_box()), and(a(), right.right.clone_box()))));
+    }
+    if let Some(left) = binary.left.as_binary().filter(|left| left.op == BinaryOperator::OR) {
+        let c = || binary.right.clone_box();
+        return Some(("distributivity", or(and(left.left.clone_box(), c()), and(left.right.clone_box(), c()))));
+    }
+    None
+}
+
+/*
+ * Normal forms
+ */
+
+/// Orders and reduces the terms of a disjunctive normal form, each law applied to all terms at
+/// once.
+fn normalize(terms: Vec<Term>, steps: &mut Vec<Step>) -> Vec<Term> {
+    let terms = record(terms, "complement", steps, |terms| terms.into_iter().filter(|term| !term.iter().any(|(var, positive)| term.contains(&(var.clone(), !positive)))).collect());
+    let terms = record(terms, "idempotence", steps, |terms| terms.into_iter().map(|term| {
+        let mut result: Term = Vec::new();
+        for literal in term {
+            if !result.contains(&literal) {
+                result.push(literal);
+            }
+        }
+        result
+    }).collect());
+    let terms = sort(terms, steps);
+    let terms = record(terms, "absorption", steps, |terms| terms.iter().filter(|term| {
+        !terms.iter().any(|other| other.len() < term.len() && other.iter().all(|literal| term.contains(literal)))
+    }).cloned().collect());
+    terms
+}
+
+/// Expands each term to all variables, which results in the canonical disjunctive normal form.
+/// Each missing variable doubles a term, so the size of the result is checked first.
+fn expand(terms: Vec<Term>, variables: &BTreeSet<String>, steps: &mut Vec<Step>) -> Result<Vec<Term>, String> {
+    let count = terms.iter().fold(0usize, |count, term| {
+        let missing = variables.iter().filter(|var| !term.iter().any(|(name, _)| name == *var)).count();
+        count.saturating_add(if missing < usize::BITS as usize { 1 << missing } else { usize::MAX })
+    });
+    if steps.len().saturating_add(count) > MAX_STEPS {
+        return Err(format!("the explanation takes more than {} steps", MAX_STEPS));
+    }
+    let terms = record(terms, "complement and distributivity", steps, |terms| {
+        let mut result = Vec::new();
+        for term in terms {
+            let mut expanded = vec![term];
+            for var in variables {
+                if expanded[0].iter().any(|(name, _)| name == var) {
+                    continue;
+                }
+                expanded = expanded.into_iter().flat_map(|term| [true, false].map(|positive| {
+                    let mut term = term.clone();
+                    term.push((var.clone(), positive));
+                    term
+                })).collect();
+            }
+            result.extend(expanded);
+        }
+        result
+    });
+    Ok(sort(terms, steps))
+}
+
+/// Orders the literals and the terms and removes duplicate terms.
+fn sort(terms: Vec<Term>, steps: &mut Vec<Step>) -> Vec<Term> {
+    let terms = record(terms, "commutativity", steps, |mut terms| {
+        for term in terms.iter_mut() {
+            term.sort_by(|(a, a_positive), (b, b_positive)| a.cmp(b).then(b_positive.cmp(a_positive)));
+        }
+        terms.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.iter().map(|(var, positive)| (var, !positive)).cmp(b.iter().map(|(var, positive)| (var, !positive)))));
+        terms
+    });
+    record(terms, "idempotence", steps, |mut terms| {
+        terms.dedup();
+        terms
+    })
+}
+
+/// Applies the law to the terms and records a step if that changed the expression.
+fn record(terms: Vec<Term>, law: &'static str, steps: &mut Vec<Step>, apply: impl FnOnce(Vec<Term>) -> Vec<Term>) -> Vec<Term> {
+    let before = to_expression(&terms).to_string();
+    let terms = apply(terms);
+    let expr = to_expression(&terms);
+    if expr.to_string() != before {
+        steps.push(Step { law, expr });
+    }
+    terms
+}
+
+/// Reads the terms of an expression that consists of disjunctions of conjunctions of literals.
+fn to_terms(expr: &dyn Expression) -> Vec<Term> {
+    if let Some(value) = expr.as_value() {
+        return if value.value { vec![vec![]] } else { vec![] };
+    }
+    let mut disjuncts = Vec::new();
+    flatten(BinaryOperator::OR, expr.clone_box(), &mut disjuncts);
+    disjuncts.iter().map(|disjunct| {
+        let mut literals = Vec::new();
+        flatten(BinaryOperator::AND, disjunct.clone_box(), &mut literals);
+        // The literals are variables, their names are taken unquoted
+        let name = |expr: &dyn Expression| expr.as_variable().map_or_else(|| expr.to_string(), |var| var.name.clone());
+        literals.iter().map(|literal| match literal.as_unary() {
+            Some(unary) => (name(unary.arg.as_ref()), false),
+            None => (name(literal.as_ref()), true)
+        }).collect()
+    }).collect()
+}
+
+fn to_expression(terms: &[Term]) -> Box<dyn Expression> {
+    let term = |term: &Term| -> Box<dyn Expression> {
+        let mut literals = term.iter().map(|(var, positive)| -> Box<dyn Expression> {
+            let variable = Box::new(Variable::new(var));
+            if *positive { variable } else { not(variable) }
+        });
+        match literals.next() {
+            Some(first) => literals.fold(first, and),
+            None => value_of(true)
+        }
+    };
+    let mut disjuncts = terms.iter().map(term);
+    match disjuncts.next() {
+        Some(first) => disjuncts.fold(first, or),
+        None => value_of(false)
+    }
+}
+
+fn value_of(value: bool) -> Box<dyn Expression> {
+    Box::new(Value::new(value))
+}
+
+fn not(arg: Box<dyn Expression>) -> Box<dyn Expression> {
+    Box::new(UnaryExpression::new(UnaryOperator::NEG, arg))
+}
+
+fn and(left: Box<dyn Expression>, right: Box<dyn Expression>) -> Box<dyn Expression> {
+    Box::new(BinaryExpression::new(BinaryOperator::AND, left, right))
+}
+
+fn or(left: Box<dyn Expression>, right: Box<dyn Expression>) -> Box<dyn Expression> {
+    Box::new(BinaryExpression::new(BinaryOperator::OR, left, right))
+}
+
+/*
+ * Tests
+ */
+
+#[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
+mod tests {
+    use super::*;
+    use crate::expression::assert_equivalent;
+    use crate::parser::parse;
+    use crate::tokens::tokenize;
+
+    fn parsed(str: &str) -> Box<dyn Expression> {
+        parse(&tokenize(str).unwrap_or_else(|_| vec![])).ok().unwrap()
+    }
+
+    fn laws(steps: &[Step]) -> Vec<&'static str> {
+        steps.iter().map(|step| step.law).collect()
+    }
+
+    #[test]
+    fn explain_dnf_names_the_laws() {
+        let steps = explain_dnf(parsed("!(a & !b)").as_ref()).ok().unwrap();
+        assert_eq!(laws(&steps), vec!["De Morgan", "double negation"]);
+        assert_eq!(steps[0].expr.to_string(), "!a | !(!b)");
+        assert_eq!(steps[1].expr.to_string(), "!a | b");
+
+        let steps = explain_dnf(parsed("a & (b | a)").as_ref()).ok().unwrap();
+        assert_eq!(laws(&steps), vec!["absorption"]);
+
+        let steps = explain_dnf(parsed("(a => b) & c").as_ref()).ok().unwrap();
+        assert_eq!(laws(&steps), vec!["definition of =>", "distributivity"]);
+        assert_eq!(steps[1].expr.to_string(), "!a & c | b & c");
+
+        let steps = explain_dnf(parsed("b | a & a").as_ref()).ok().unwrap();
+        assert_eq!(laws(&steps), vec!["idempotence", "commutativity"]);
+        assert_eq!(steps[1].expr.to_string(), "a | b");
+    }
+
+    #[test]
+    fn explain_dnf_handles_all_operators() {
+        for str in ["a ^ b", "a !^ b", "a !& b", "a !| b", "a <= b", "a !=> b", "s ? a : b", "maj(a, b, c)",
+            "exactly(1; a, b, c)", "atmost(1; a, b)", "parity(a, b)", "exists x. x & a", "forall x. x | a"] {
+            let expr = parsed(str);
+            let steps = explain_dnf(expr.as_ref()).ok().unwrap();
+            assert_equivalent(expr.as_ref(), steps.last().unwrap().expr.as_ref());
+        }
+    }
+
+    #[test]
+    fn explain_equivalence_ends_with_the_target() {
+        let steps = explain_equivalence(parsed("a => b").as_ref(), parsed("!b => !a").as_ref()).ok().unwrap();
+        assert_eq!(steps.last().unwrap().expr.to_string(), "!b => !a");
+        assert_eq!(laws(&steps).contains(&"definition of =>"), true);
+
+        let steps = explain_equivalence(parsed("a | !a & b").as_ref(), parsed("a | b").as_ref()).ok().unwrap();
+        assert_eq!(steps.last().unwrap().expr.to_string(), "a | b");
+
+        assert_eq!(explain_equivalence(parsed("a => b").as_ref(), parsed("b => a").as_ref()).is_err(), true);
+
+        let steps = explain_equivalence(parsed("\"bus[3]\" | a").as_ref(), parsed("a | \"bus[3]\"").as_ref()).ok().unwrap();
+        assert_eq!(laws(&steps), vec!["commutativity"]);
+        assert_eq!(steps[0].expr.to_string(), "a | \"bus[3]\"");
+    }
+
+    #[test]
+    fn explain_equivalence_expands_only_if_necessary() {
+        let vars: Vec<String> = (0..18).map(|i| format!("v{}", i)).collect();
+        let expr = parsed(&vars.join(" | "));
+        assert_eq!(explain_equivalence(expr.as_ref(), expr.as_ref()).ok().unwrap().len(), 0);
+
+        let reversed = parsed(&vars.iter().rev().cloned().collect::<Vec<_>>().join(" | "));
+        let steps = explain_equivalence(reversed.as_ref(), expr.as_ref()).ok().unwrap();
+        assert_eq!(steps.last().unwrap().expr.to_string(), expr.to_string());
+
+        // Only the canonical forms show that `v0 | !v0 & v1` is `v0 | v1`
+        let longer = parsed(&format!("v0 | !v0 & {}", vars[1..].join(" | ")));
+        assert_eq!(explain_equivalence(longer.as_ref(), expr.as_ref()).err().unwrap(), "the explanation takes more than 2000 steps");
+    }
+    #[test]
+    fn format_steps_aligns_the_laws() {
+        let expr = parsed("!!a & 1");
+        let steps = explain_dnf(expr.as_ref()).ok().unwrap();
+        assert_eq!(format_steps(expr.as_ref(), &steps), "  !(!a) & 1\n= !(!a)   (identity)\n= a       (double negation)\n");
+
+        // Wide characters take two columns
+        let expr = parsed("!!(名名 | 1) & !!a");
+        let steps = explain_dnf(expr.as_ref()).ok().unwrap();
+        assert_eq!(format_steps(expr.as_ref(), &steps), "  !(!(名名 | 1)) & !(!a)\n= (名名 | 1) & !(!a)   (double negation)\n\
+            = 1 & !(!a)            (annihilator)\n= !(!a)                (identity)\n= a                    (double negation)\n");
+    }
+}
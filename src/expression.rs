@@ -1,9 +1,13 @@
+use std::cell::RefCell;
 use std::collections::{BTreeMap, BTreeSet};
 
+use crate::tokens::{is_identifier, SourceRange};
+
 /*
  * EvaluationContext
  */
 
+#[derive(Clone)]
 pub struct EvaluationContext {
     pub variables: BTreeSet<String>,
     pub not_preset: BTreeSet<String>,
@@ -46,6 +50,13 @@ impl EvaluationContext {
             self.values.insert(var.to_string(), (values & (1 << i)) != 0);
         }
     }
+
+    /// Returns a copy of this context with the given (bound) variable set to the given value.
+    pub fn bind(&self, name: &str, value: bool) -> EvaluationContext {
+        let mut result = self.clone();
+        result.values.insert(String::from(name), value);
+        result
+    }
 }
 
 /*
@@ -57,20 +68,64 @@ pub trait Expression {
 
     fn precedence(&self) -> usize;
 
-    fn traverse(&self, callback: &dyn Fn(&dyn Expression)) -> ();
+    fn traverse(&self, callback: &dyn Fn(&dyn Expression));
+
+    fn children(&self) -> Vec<&dyn Expression>;
+
+    fn clone_box(&self) -> Box<dyn Expression>;
+
+    /// Returns a copy of this expression with the variables in `bindings` replaced by the bound expressions.
+    fn substitute(&self, bindings: &BTreeMap<String, Box<dyn Expression>>) -> Box<dyn Expression>;
+
+    /// Returns the part of the source the expression was parsed from, `None` if it was created
+    /// otherwise.
+    fn range(&self) -> Option<SourceRange>;
+
+    fn set_range(&mut self, range: Option<SourceRange>);
 
     fn as_variable(&self) -> Option<&Variable> {
         None
     }
 
+    fn as_value(&self) -> Option<&Value> {
+        None
+    }
+
+    fn as_unary(&self) -> Option<&UnaryExpression> {
+        None
+    }
+
+    fn as_binary(&self) -> Option<&BinaryExpression> {
+        None
+    }
+
+    fn as_conditional(&self) -> Option<&ConditionalExpression> {
+        None
+    }
+
+    fn as_cardinality(&self) -> Option<&CardinalityExpression> {
+        None
+    }
+
+    fn as_quantified(&self) -> Option<&QuantifiedExpression> {
+        None
+    }
+
     fn to_string(&self) -> String;
 
+    #[allow(dead_code)]
     fn to_dump_string(&self) -> String {
         self.to_string()
     }
 }
 
-fn to_string(expr: &Box<dyn Expression>, parent_precedence: usize) -> String {
+/// Returns the expression with the given source range.
+pub fn with_range(mut expr: Box<dyn Expression>, range: Option<SourceRange>) -> Box<dyn Expression> {
+    expr.set_range(range);
+    expr
+}
+
+fn to_string(expr: &dyn Expression, parent_precedence: usize) -> String {
     if expr.precedence() > parent_precedence {
         expr.to_string()
     } else {
@@ -78,19 +133,154 @@ fn to_string(expr: &Box<dyn Expression>, parent_precedence: usize) -> String {
     }
 }
 
+/// Returns the variables of the expression that are not bound by a quantifier.
+pub fn free_variables(expr: &dyn Expression) -> BTreeSet<String> {
+    let mut variables = BTreeSet::new();
+    collect_free_variables(expr, &mut Vec::new(), &mut variables);
+    variables
+}
+
+fn collect_free_variables(expr: &dyn Expression, scope: &mut Vec<String>, variables: &mut BTreeSet<String>) {
+    if let Some(var) = expr.as_variable() {
+        if !scope.contains(&var.name) {
+            variables.insert(var.name.clone());
+        }
+    } else if let Some(quantified) = expr.as_quantified() {
+        scope.push(quantified.var.clone());
+        collect_free_variables(quantified.body.as_ref(), scope, variables);
+        scope.pop();
+    } else {
+        for child in expr.children() {
+            collect_free_variables(child, scope, variables);
+        }
+    }
+}
+
+/// Returns the variables bound by a quantifier somewhere in the expression.
+pub fn bound_variables(expr: &dyn Expression) -> BTreeSet<String> {
+    let mut variables = BTreeSet::new();
+    let names = RefCell::new(&mut variables);
+    expr.traverse(&|e| {
+        if let Some(quantified) = e.as_quantified() {
+            names.borrow_mut().insert(quantified.var.clone());
+        }
+    });
+    variables
+}
+
+
+/// Returns the first occurrence of the variable that is not bound by a quantifier.
+pub fn find_free_occurrence<'a>(expr: &'a dyn Expression, name: &str) -> Option<&'a dyn Expression> {
+    if let Some(var) = expr.as_variable() {
+        return if var.name == name { Some(expr) } else { None };
+    }
+    if expr.as_quantified().is_some_and(|quantified| quantified.var == name) {
+        return None;
+    }
+    expr.children().into_iter().find_map(|child| find_free_occurrence(child, name))
+}
+
+/// Returns the first quantifier binding the variable.
+pub fn find_binding<'a>(expr: &'a dyn Expression, name: &str) -> Option<&'a QuantifiedExpression> {
+    match expr.as_quantified() {
+        Some(quantified) if quantified.var == name => Some(quantified),
+        _ => expr.children().into_iter().find_map(|child| find_binding(child, name))
+    }
+}
+
+/// Returns the expression with the free occurrences of the variable replaced by `replacement`,
+/// e.g. `c := x & y` turns `a ^ !c` into `a ^ !(x & y)`.
+pub fn substitute_variable(expr: &dyn Expression, name: &str, replacement: &dyn Expression) -> Box<dyn Expression> {
+    let mut bindings: BTreeMap<String, Box<dyn Expression>> = BTreeMap::new();
+    bindings.insert(name.to_string(), replacement.clone_box());
+    expr.substitute(&bindings)
+}
+
+/// Returns a copy of the expression with the child at `index` replaced.
+pub fn replace_child(expr: &dyn Expression, index: usize, child: Box<dyn Expression>) -> Box<dyn Expression> {
+    if let Some(unary) = expr.as_unary() {
+        Box::new(UnaryExpression::new(unary.op, child))
+    } else if let Some(binary) = expr.as_binary() {
+        if index == 0 {
+            Box::new(BinaryExpression::new(binary.op, child, binary.right.clone_box()))
+        } else {
+            Box::new(BinaryExpression::new(binary.op, binary.left.clone_box(), child))
+        }
+    } else if let Some(conditional) = expr.as_conditional() {
+        let mut parts = vec![conditional.condition.clone_box(), conditional.if_true.clone_box(), conditional.if_false.clone_box()];
+        parts[index] = child;
+        let if_false = parts.pop().unwrap();
+        let if_true = parts.pop().unwrap();
+        Box::new(ConditionalExpression::new(parts.pop().unwrap(), if_true, if_false))
+    } else if let Some(cardinality) = expr.as_cardinality() {
+        let mut args: Vec<Box<dyn Expression>> = cardinality.args.iter().map(|arg| arg.clone_box()).collect();
+        args[index] = child;
+        Box::new(CardinalityExpression::new(cardinality.op, cardinality.k, args))
+    } else if let Some(quantified) = expr.as_quantified() {
+        Box::new(QuantifiedExpression::new(quantified.quantifier, &quantified.var, child))
+    } else {
+        child
+    }
+}
+
+/// Expands a cardinality function by its first argument (Shannon expansion); `maj` is replaced
+/// by `atleast` first and a function decided by the number of arguments by a constant.
+pub fn expand_cardinality(cardinality: &CardinalityExpression) -> Box<dyn Expression> {
+    let args: Vec<Box<dyn Expression>> = cardinality.args.iter().map(|arg| arg.clone_box()).collect();
+    let count = args.len();
+    if cardinality.op == CardinalityOperator::MAJ {
+        return Box::new(CardinalityExpression::new(CardinalityOperator::ATLEAST, count / 2 + 1, args));
+    }
+    let decided = match cardinality.op {
+        _ if count == 0 => Some(cardinality.eval(&EvaluationContext::new(BTreeSet::new()))),
+        CardinalityOperator::ATLEAST if cardinality.k == 0 => Some(true),
+        CardinalityOperator::ATLEAST | CardinalityOperator::EXACTLY if cardinality.k > count => Some(false),
+        CardinalityOperator::ATMOST if cardinality.k >= count => Some(true),
+        _ => None
+    };
+    if let Some(value) = decided {
+        return Box::new(Value::new(value));
+    }
+
+    let not = |arg: Box<dyn Expression>| -> Box<dyn Expression> { Box::new(UnaryExpression::new(UnaryOperator::NEG, arg)) };
+    let and = |left: Box<dyn Expression>, right: Box<dyn Expression>| -> Box<dyn Expression> { Box::new(BinaryExpression::new(BinaryOperator::AND, left, right)) };
+    let rest = || args[1..].iter().map(|arg| arg.clone_box()).collect::<Vec<_>>();
+    let counted = |k: usize| -> Box<dyn Expression> { Box::new(CardinalityExpression::new(cardinality.op, k, rest())) };
+    let (if_true, if_false) = match cardinality.op {
+        CardinalityOperator::PARITY => (not(counted(0)), counted(0)),
+        CardinalityOperator::ATLEAST => (counted(cardinality.k - 1), counted(cardinality.k)),
+        _ if cardinality.k == 0 => (Box::new(Value::new(false)) as Box<dyn Expression>, counted(0)),
+        _ => (counted(cardinality.k - 1), counted(cardinality.k))
+    };
+    Box::new(BinaryExpression::new(BinaryOperator::OR, and(args[0].clone_box(), if_true), and(not(args[0].clone_box()), if_false)))
+}
+
+/// Asserts that the expressions have the same value for all assignments of their variables.
+#[cfg(test)]
+pub fn assert_equivalent(expected: &dyn Expression, actual: &dyn Expression) {
+    let mut variables = free_variables(expected);
+    variables.extend(free_variables(actual));
+    let count = variables.len();
+    let mut ctxt = EvaluationContext::new(variables);
+    for i in 0..(1u128 << count) {
+        ctxt.set_not_presets(i);
+        assert_eq!(actual.eval(&ctxt), expected.eval(&ctxt), "{} is not equivalent to {}", actual.to_string(), expected.to_string());
+    }
+}
 
 /*
  * Value
  */
 
 pub struct Value {
-    value: bool
+    pub value: bool,
+    pub range: Option<SourceRange>,
 }
 
 
 impl Value {
     pub fn new(value: bool) -> Value {
-        Value { value }
+        Value { value, range: None }
     }
 }
 
@@ -99,12 +289,36 @@ impl Expression for Value {
         self.value
     }
 
-    fn precedence(&self) -> usize { 4 }
+    fn precedence(&self) -> usize { 5 }
 
-    fn traverse(&self, callback: &dyn Fn(&dyn Expression)) -> () {
+    fn traverse(&self, callback: &dyn Fn(&dyn Expression)) {
         callback(self);
     }
 
+    fn children(&self) -> Vec<&dyn Expression> {
+        Vec::new()
+    }
+
+    fn clone_box(&self) -> Box<dyn Expression> {
+        with_range(Box::new(Value::new(self.value)), self.range)
+    }
+
+    fn substitute(&self, _bindings: &BTreeMap<String, Box<dyn Expression>>) -> Box<dyn Expression> {
+        self.clone_box()
+    }
+
+    fn range(&self) -> Option<SourceRange> {
+        self.range
+    }
+
+    fn set_range(&mut self, range: Option<SourceRange>) {
+        self.range = range;
+    }
+
+    fn as_value(&self) -> Option<&Value> {
+        Some(self)
+    }
+
     fn to_string(&self) -> String {
         if self.value { String::from("1") } else { String::from("0") }
     }
@@ -120,12 +334,13 @@ impl Expression for Value {
  */
 
 pub struct Variable {
-    pub name: String
+    pub name: String,
+    pub range: Option<SourceRange>,
 }
 
 impl Variable {
     pub fn new(name: &str) -> Variable {
-        Variable { name: name.to_string() }
+        Variable { name: name.to_string(), range: None }
     }
 }
 
@@ -134,22 +349,49 @@ impl Expression for Variable {
         ctxt.get(&self.name)
     }
 
-    fn precedence(&self) -> usize { 4 }
+    fn precedence(&self) -> usize { 5 }
 
-    fn traverse(&self, callback: &dyn Fn(&dyn Expression)) -> () {
+    fn traverse(&self, callback: &dyn Fn(&dyn Expression)) {
         callback(self);
     }
 
+    fn children(&self) -> Vec<&dyn Expression> {
+        Vec::new()
+    }
+
+    fn clone_box(&self) -> Box<dyn Expression> {
+        with_range(Box::new(Variable::new(&self.name)), self.range)
+    }
+
+    fn substitute(&self, bindings: &BTreeMap<String, Box<dyn Expression>>) -> Box<dyn Expression> {
+        match bindings.get(&self.name) {
+            Some(expr) => expr.clone_box(),
+            None => self.clone_box()
+        }
+    }
+
+    fn range(&self) -> Option<SourceRange> {
+        self.range
+    }
+
+    fn set_range(&mut self, range: Option<SourceRange>) {
+        self.range = range;
+    }
+
     fn as_variable(&self) -> Option<&Variable> {
-        Some(&self)
+        Some(self)
     }
 
     fn to_string(&self) -> String {
-        String::from(&self.name)
+        if is_identifier(&self.name) {
+            String::from(&self.name)
+        } else {
+            format!("\"{}\"", &self.name)
+        }
     }
 
     fn to_dump_string(&self) -> String {
-        String::from(format!("Variable({})", &self.name))
+        format!("Variable({})", &self.name)
     }
 }
 
@@ -158,18 +400,21 @@ impl Expression for Variable {
  * UnaryOperator/Expression
  */
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum UnaryOperator {
     NEG
 }
 
 pub struct UnaryExpression {
-    op: UnaryOperator,
-    arg: Box<dyn Expression>,
+    pub op: UnaryOperator,
+    pub arg: Box<dyn Expression>,
+    pub range: Option<SourceRange>,
 }
 
 impl UnaryExpression {
     pub fn new(op: UnaryOperator, arg: Box<dyn Expression>) -> UnaryExpression {
-        UnaryExpression { op, arg }
+        UnaryExpression { op, arg, range: None }
     }
 }
 
@@ -180,20 +425,44 @@ impl Expression for UnaryExpression {
         }
     }
 
-    fn traverse(&self, callback: &dyn Fn(&dyn Expression)) -> () {
+    fn traverse(&self, callback: &dyn Fn(&dyn Expression)) {
         callback(self);
         self.arg.traverse(callback);
     }
 
+    fn children(&self) -> Vec<&dyn Expression> {
+        vec![self.arg.as_ref()]
+    }
+
+    fn clone_box(&self) -> Box<dyn Expression> {
+        with_range(Box::new(UnaryExpression::new(self.op, self.arg.clone_box())), self.range)
+    }
+
+    fn substitute(&self, bindings: &BTreeMap<String, Box<dyn Expression>>) -> Box<dyn Expression> {
+        with_range(Box::new(UnaryExpression::new(self.op, self.arg.substitute(bindings))), self.range)
+    }
+
     fn precedence(&self) -> usize {
         match self.op {
-            UnaryOperator::NEG => 3
+            UnaryOperator::NEG => 4
         }
     }
 
+    fn range(&self) -> Option<SourceRange> {
+        self.range
+    }
+
+    fn set_range(&mut self, range: Option<SourceRange>) {
+        self.range = range;
+    }
+
+    fn as_unary(&self) -> Option<&UnaryExpression> {
+        Some(self)
+    }
+
     fn to_string(&self) -> String {
         match self.op {
-            UnaryOperator::NEG => format!("!{}", to_string(&self.arg, self.precedence()))
+            UnaryOperator::NEG => format!("!{}", to_string(self.arg.as_ref(), self.precedence()))
         }
     }
 
@@ -209,23 +478,31 @@ impl Expression for UnaryExpression {
  * BinaryOperator/Expression
  */
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum BinaryOperator {
     OR,
     AND,
     XOR,
     IMP,
     EQ,
+    NAND,
+    NOR,
+    XNOR,
+    RIMP,
+    NIMP,
 }
 
 pub struct BinaryExpression {
-    op: BinaryOperator,
-    left: Box<dyn Expression>,
-    right: Box<dyn Expression>,
+    pub op: BinaryOperator,
+    pub left: Box<dyn Expression>,
+    pub right: Box<dyn Expression>,
+    pub range: Option<SourceRange>,
 }
 
 impl BinaryExpression {
     pub fn new(op: BinaryOperator, left: Box<dyn Expression>, right: Box<dyn Expression>) -> BinaryExpression {
-        BinaryExpression { op, left, right }
+        BinaryExpression { op, left, right, range: None }
     }
 }
 
@@ -236,35 +513,74 @@ impl Expression for BinaryExpression {
             BinaryOperator::XOR => self.left.eval(ctxt) != self.right.eval(ctxt),
             BinaryOperator::AND => self.left.eval(ctxt) && self.right.eval(ctxt),
             BinaryOperator::EQ => self.left.eval(ctxt) == self.right.eval(ctxt),
-            BinaryOperator::IMP => !self.left.eval(ctxt) || self.right.eval(ctxt)
+            BinaryOperator::IMP => !self.left.eval(ctxt) || self.right.eval(ctxt),
+            BinaryOperator::NAND => !(self.left.eval(ctxt) && self.right.eval(ctxt)),
+            BinaryOperator::NOR => !(self.left.eval(ctxt) || self.right.eval(ctxt)),
+            BinaryOperator::XNOR => self.left.eval(ctxt) == self.right.eval(ctxt),
+            BinaryOperator::RIMP => self.left.eval(ctxt) || !self.right.eval(ctxt),
+            BinaryOperator::NIMP => self.left.eval(ctxt) && !self.right.eval(ctxt)
         }
     }
 
     fn precedence(&self) -> usize {
         match self.op {
-            BinaryOperator::OR => 1,
-            BinaryOperator::XOR => 1,
-            BinaryOperator::AND => 2,
-            BinaryOperator::EQ => 0,
-            BinaryOperator::IMP => 0
+            BinaryOperator::OR => 2,
+            BinaryOperator::XOR => 2,
+            BinaryOperator::AND => 3,
+            BinaryOperator::EQ => 1,
+            BinaryOperator::IMP => 1,
+            BinaryOperator::NAND => 3,
+            BinaryOperator::NOR => 2,
+            BinaryOperator::XNOR => 2,
+            BinaryOperator::RIMP => 1,
+            BinaryOperator::NIMP => 1
         }
     }
 
-    fn traverse(&self, callback: &dyn Fn(&dyn Expression)) -> () {
+    fn traverse(&self, callback: &dyn Fn(&dyn Expression)) {
         callback(self);
         self.left.traverse(callback);
         self.right.traverse(callback);
     }
 
+    fn children(&self) -> Vec<&dyn Expression> {
+        vec![self.left.as_ref(), self.right.as_ref()]
+    }
+
+    fn clone_box(&self) -> Box<dyn Expression> {
+        with_range(Box::new(BinaryExpression::new(self.op, self.left.clone_box(), self.right.clone_box())), self.range)
+    }
+
+    fn substitute(&self, bindings: &BTreeMap<String, Box<dyn Expression>>) -> Box<dyn Expression> {
+        with_range(Box::new(BinaryExpression::new(self.op, self.left.substitute(bindings), self.right.substitute(bindings))), self.range)
+    }
+
+    fn range(&self) -> Option<SourceRange> {
+        self.range
+    }
+
+    fn set_range(&mut self, range: Option<SourceRange>) {
+        self.range = range;
+    }
+
+    fn as_binary(&self) -> Option<&BinaryExpression> {
+        Some(self)
+    }
+
     fn to_string(&self) -> String {
-        let left = to_string(&self.left, self.precedence());
-        let right = to_string(&self.right, self.precedence());
+        let left = to_string(self.left.as_ref(), self.precedence());
+        let right = to_string(self.right.as_ref(), self.precedence());
         match self.op {
             BinaryOperator::OR => format!("{} | {}", left, right),
             BinaryOperator::XOR => format!("{} ^ {}", left, right),
             BinaryOperator::AND => format!("{} & {}", left, right),
             BinaryOperator::EQ => format!("{} = {}", left, right),
-            BinaryOperator::IMP => format!("{} => {}", left, right)
+            BinaryOperator::IMP => format!("{} => {}", left, right),
+            BinaryOperator::NAND => format!("{} !& {}", left, right),
+            BinaryOperator::NOR => format!("{} !| {}", left, right),
+            BinaryOperator::XNOR => format!("{} !^ {}", left, right),
+            BinaryOperator::RIMP => format!("{} <= {}", left, right),
+            BinaryOperator::NIMP => format!("{} !=> {}", left, right)
         }
     }
 
@@ -274,7 +590,296 @@ impl Expression for BinaryExpression {
             BinaryOperator::XOR => format!("Xor({},{})", self.left.to_dump_string(), self.right.to_dump_string()),
             BinaryOperator::AND => format!("And({},{})", self.left.to_dump_string(), self.right.to_dump_string()),
             BinaryOperator::EQ => format!("Eq({},{})", self.left.to_dump_string(), self.right.to_dump_string()),
-            BinaryOperator::IMP => format!("Imp({},{})", self.left.to_dump_string(), self.right.to_dump_string())
+            BinaryOperator::IMP => format!("Imp({},{})", self.left.to_dump_string(), self.right.to_dump_string()),
+            BinaryOperator::NAND => format!("Nand({},{})", self.left.to_dump_string(), self.right.to_dump_string()),
+            BinaryOperator::NOR => format!("Nor({},{})", self.left.to_dump_string(), self.right.to_dump_string()),
+            BinaryOperator::XNOR => format!("Xnor({},{})", self.left.to_dump_string(), self.right.to_dump_string()),
+            BinaryOperator::RIMP => format!("Rimp({},{})", self.left.to_dump_string(), self.right.to_dump_string()),
+            BinaryOperator::NIMP => format!("Nimp({},{})", self.left.to_dump_string(), self.right.to_dump_string())
+        }
+    }
+}
+
+
+/*
+ * ConditionalExpression
+ */
+
+pub struct ConditionalExpression {
+    pub condition: Box<dyn Expression>,
+    pub if_true: Box<dyn Expression>,
+    pub if_false: Box<dyn Expression>,
+    pub range: Option<SourceRange>,
+}
+
+impl ConditionalExpression {
+    pub fn new(condition: Box<dyn Expression>, if_true: Box<dyn Expression>, if_false: Box<dyn Expression>) -> ConditionalExpression {
+        ConditionalExpression { condition, if_true, if_false, range: None }
+    }
+}
+
+impl Expression for ConditionalExpression {
+    fn eval(&self, ctxt: &EvaluationContext) -> bool {
+        if self.condition.eval(ctxt) {
+            self.if_true.eval(ctxt)
+        } else {
+            self.if_false.eval(ctxt)
+        }
+    }
+
+    fn precedence(&self) -> usize { 0 }
+
+    fn traverse(&self, callback: &dyn Fn(&dyn Expression)) {
+        callback(self);
+        self.condition.traverse(callback);
+        self.if_true.traverse(callback);
+        self.if_false.traverse(callback);
+    }
+
+    fn children(&self) -> Vec<&dyn Expression> {
+        vec![self.condition.as_ref(), self.if_true.as_ref(), self.if_false.as_ref()]
+    }
+
+    fn clone_box(&self) -> Box<dyn Expression> {
+        with_range(Box::new(ConditionalExpression::new(self.condition.clone_box(), self.if_true.clone_box(), self.if_false.clone_box())), self.range)
+    }
+
+    fn substitute(&self, bindings: &BTreeMap<String, Box<dyn Expression>>) -> Box<dyn Expression> {
+        with_range(Box::new(ConditionalExpression::new(
+            self.condition.substitute(bindings),
+            self.if_true.substitute(bindings),
+            self.if_false.substitute(bindings))), self.range)
+    }
+
+    fn range(&self) -> Option<SourceRange> {
+        self.range
+    }
+
+    fn set_range(&mut self, range: Option<SourceRange>) {
+        self.range = range;
+    }
+
+    fn as_conditional(&self) -> Option<&ConditionalExpression> {
+        Some(self)
+    }
+
+    fn to_string(&self) -> String {
+        // The else branch binds to the right, so only nested conditions and then branches need paranthesis
+        format!("{} ? {} : {}",
+                to_string(self.condition.as_ref(), self.precedence()),
+                to_string(self.if_true.as_ref(), self.precedence()),
+                self.if_false.to_string())
+    }
+
+    fn to_dump_string(&self) -> String {
+        format!("Ite({},{},{})", self.condition.to_dump_string(), self.if_true.to_dump_string(), self.if_false.to_dump_string())
+    }
+}
+
+
+/*
+ * CardinalityOperator/Expression
+ */
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum CardinalityOperator {
+    MAJ,
+    ATLEAST,
+    ATMOST,
+    EXACTLY,
+    PARITY,
+}
+
+pub struct CardinalityExpression {
+    pub op: CardinalityOperator,
+    pub k: usize,
+    pub args: Vec<Box<dyn Expression>>,
+    pub range: Option<SourceRange>,
+}
+
+impl CardinalityExpression {
+    pub fn new(op: CardinalityOperator, k: usize, args: Vec<Box<dyn Expression>>) -> CardinalityExpression {
+        CardinalityExpression { op, k, args, range: None }
+    }
+
+    fn name(&self) -> &str {
+        match self.op {
+            CardinalityOperator::MAJ => "maj",
+            CardinalityOperator::ATLEAST => "atleast",
+            CardinalityOperator::ATMOST => "atmost",
+            CardinalityOperator::EXACTLY => "exactly",
+            CardinalityOperator::PARITY => "parity"
+        }
+    }
+
+    fn has_bound(&self) -> bool {
+        !matches!(self.op, CardinalityOperator::MAJ | CardinalityOperator::PARITY)
+    }
+}
+
+impl Expression for CardinalityExpression {
+    fn eval(&self, ctxt: &EvaluationContext) -> bool {
+        let count = self.args.iter().filter(|arg| arg.eval(ctxt)).count();
+        match self.op {
+            CardinalityOperator::MAJ => 2 * count > self.args.len(),
+            CardinalityOperator::ATLEAST => count >= self.k,
+            CardinalityOperator::ATMOST => count <= self.k,
+            CardinalityOperator::EXACTLY => count == self.k,
+            CardinalityOperator::PARITY => count % 2 == 1
+        }
+    }
+
+    fn precedence(&self) -> usize { 5 }
+
+    fn traverse(&self, callback: &dyn Fn(&dyn Expression)) {
+        callback(self);
+        for arg in &self.args {
+            arg.traverse(callback);
+        }
+    }
+
+    fn children(&self) -> Vec<&dyn Expression> {
+        self.args.iter().map(|arg| arg.as_ref()).collect()
+    }
+
+    fn clone_box(&self) -> Box<dyn Expression> {
+        with_range(Box::new(CardinalityExpression::new(self.op, self.k, self.args.iter().map(|arg| arg.clone_box()).collect())), self.range)
+    }
+
+    fn substitute(&self, bindings: &BTreeMap<String, Box<dyn Expression>>) -> Box<dyn Expression> {
+        with_range(Box::new(CardinalityExpression::new(self.op, self.k, self.args.iter().map(|arg| arg.substitute(bindings)).collect())), self.range)
+    }
+
+    fn range(&self) -> Option<SourceRange> {
+        self.range
+    }
+
+    fn set_range(&mut self, range: Option<SourceRange>) {
+        self.range = range;
+    }
+
+    fn as_cardinality(&self) -> Option<&CardinalityExpression> {
+        Some(self)
+    }
+
+    fn to_string(&self) -> String {
+        let args: Vec<String> = self.args.iter().map(|arg| arg.to_string()).collect();
+        if self.has_bound() {
+            format!("{}({}; {})", self.name(), self.k, args.join(", "))
+        } else {
+            format!("{}({})", self.name(), args.join(", "))
+        }
+    }
+
+    fn to_dump_string(&self) -> String {
+        let args: Vec<String> = self.args.iter().map(|arg| arg.to_dump_string()).collect();
+        let mut name = self.name().to_string();
+        name[..1].make_ascii_uppercase();
+        if self.has_bound() {
+            format!("{}({};{})", name, self.k, args.join(","))
+        } else {
+            format!("{}({})", name, args.join(","))
+        }
+    }
+}
+
+
+/*
+ * Quantifier/QuantifiedExpression
+ */
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum Quantifier {
+    EXISTS,
+    FORALL,
+}
+
+pub struct QuantifiedExpression {
+    pub quantifier: Quantifier,
+    pub var: String,
+    pub body: Box<dyn Expression>,
+    pub range: Option<SourceRange>,
+}
+
+impl QuantifiedExpression {
+    pub fn new(quantifier: Quantifier, var: &str, body: Box<dyn Expression>) -> QuantifiedExpression {
+        QuantifiedExpression { quantifier, var: var.to_string(), body, range: None }
+    }
+}
+
+impl Expression for QuantifiedExpression {
+    fn eval(&self, ctxt: &EvaluationContext) -> bool {
+        let if_false = self.body.eval(&ctxt.bind(&self.var, false));
+        match self.quantifier {
+            Quantifier::EXISTS => if_false || self.body.eval(&ctxt.bind(&self.var, true)),
+            Quantifier::FORALL => if_false && self.body.eval(&ctxt.bind(&self.var, true))
+        }
+    }
+
+    fn precedence(&self) -> usize { 0 }
+
+    fn traverse(&self, callback: &dyn Fn(&dyn Expression)) {
+        callback(self);
+        self.body.traverse(callback);
+    }
+
+    fn children(&self) -> Vec<&dyn Expression> {
+        vec![self.body.as_ref()]
+    }
+
+    fn clone_box(&self) -> Box<dyn Expression> {
+        with_range(Box::new(QuantifiedExpression::new(self.quantifier, &self.var, self.body.clone_box())), self.range)
+    }
+
+    fn substitute(&self, bindings: &BTreeMap<String, Box<dyn Expression>>) -> Box<dyn Expression> {
+        let mut inner: BTreeMap<String, Box<dyn Expression>> = bindings.iter()
+            .filter(|(name, _)| **name != self.var)
+            .map(|(name, expr)| (name.clone(), expr.clone_box()))
+            .collect();
+
+        // Rename the bound variable if it would capture a free variable of a substituted expression
+        let mut used = free_variables(self.body.as_ref());
+        for expr in inner.values() {
+            used.extend(free_variables(expr.as_ref()));
+        }
+        if !inner.values().any(|expr| free_variables(expr.as_ref()).contains(&self.var)) {
+            return with_range(Box::new(QuantifiedExpression::new(self.quantifier, &self.var, self.body.substitute(&inner))), self.range);
+        }
+        let mut i = 1;
+        while used.contains(&format!("{}_{}", self.var, i)) {
+            i += 1;
+        }
+        let fresh = format!("{}_{}", self.var, i);
+        inner.insert(self.var.clone(), Box::new(Variable::new(&fresh)));
+        with_range(Box::new(QuantifiedExpression::new(self.quantifier, &fresh, self.body.substitute(&inner))), self.range)
+    }
+
+    fn range(&self) -> Option<SourceRange> {
+        self.range
+    }
+
+    fn set_range(&mut self, range: Option<SourceRange>) {
+        self.range = range;
+    }
+
+    fn as_quantified(&self) -> Option<&QuantifiedExpression> {
+        Some(self)
+    }
+
+    fn to_string(&self) -> String {
+        let quantifier = match self.quantifier {
+            Quantifier::EXISTS => "exists",
+            Quantifier::FORALL => "forall"
+        };
+        format!("{} {}. {}", quantifier, Variable::new(&self.var).to_string(), self.body.to_string())
+    }
+
+    fn to_dump_string(&self) -> String {
+        match self.quantifier {
+            Quantifier::EXISTS => format!("Exists({},{})", self.var, self.body.to_dump_string()),
+            Quantifier::FORALL => format!("Forall({},{})", self.var, self.body.to_dump_string())
         }
     }
 }
@@ -285,6 +890,7 @@ impl Expression for BinaryExpression {
  */
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
 
@@ -322,6 +928,7 @@ mod tests {
         assert_eq!(expr.eval(&ctxt), false);
 
         assert_eq!(expr.to_string(), "a");
+        assert_eq!(Variable::new("bus[3]").to_string(), "\"bus[3]\"");
     }
 
     #[test]
@@ -485,4 +1092,167 @@ mod tests {
 
         assert_eq!(expr.to_string(), "a = b");
     }
+
+    fn eval_all(expr: &dyn Expression) -> Vec<bool> {
+        let mut vars = BTreeSet::new();
+        vars.insert(String::from("a"));
+        vars.insert(String::from("b"));
+        let mut ctxt = EvaluationContext::new(vars);
+        let mut result = Vec::new();
+        for (a, b) in [(false, false), (true, false), (false, true), (true, true)].iter() {
+            ctxt.set("a", *a);
+            ctxt.set("b", *b);
+            result.push(expr.eval(&ctxt));
+        }
+        result
+    }
+
+    #[test]
+    fn negated_and_reverse_operator_tests() {
+        let binary = |op| BinaryExpression::new(op, Box::new(Variable::new("a")), Box::new(Variable::new("b")));
+
+        let expr = binary(BinaryOperator::NAND);
+        assert_eq!(eval_all(&expr), vec![true, true, true, false]);
+        assert_eq!(expr.to_string(), "a !& b");
+
+        let expr = binary(BinaryOperator::NOR);
+        assert_eq!(eval_all(&expr), vec![true, false, false, false]);
+        assert_eq!(expr.to_string(), "a !| b");
+
+        let expr = binary(BinaryOperator::XNOR);
+        assert_eq!(eval_all(&expr), vec![true, false, false, true]);
+        assert_eq!(expr.to_string(), "a !^ b");
+
+        let expr = binary(BinaryOperator::RIMP);
+        assert_eq!(eval_all(&expr), vec![true, true, false, true]);
+        assert_eq!(expr.to_string(), "a <= b");
+
+        let expr = binary(BinaryOperator::NIMP);
+        assert_eq!(eval_all(&expr), vec![false, true, false, false]);
+        assert_eq!(expr.to_string(), "a !=> b");
+    }
+
+    #[test]
+    fn conditional_tests() {
+        let expr = ConditionalExpression::new(
+            Box::new(Variable::new("a")),
+            Box::new(Variable::new("b")),
+            Box::new(UnaryExpression::new(UnaryOperator::NEG, Box::new(Variable::new("b")))));
+        assert_eq!(eval_all(&expr), vec![true, false, false, true]);
+        assert_eq!(expr.to_string(), "a ? b : !b");
+
+        let nested = ConditionalExpression::new(
+            Box::new(ConditionalExpression::new(Box::new(Variable::new("a")), Box::new(Value::new(true)), Box::new(Value::new(false)))),
+            Box::new(ConditionalExpression::new(Box::new(Variable::new("b")), Box::new(Value::new(true)), Box::new(Value::new(false)))),
+            Box::new(ConditionalExpression::new(Box::new(Variable::new("b")), Box::new(Value::new(false)), Box::new(Value::new(true)))));
+        assert_eq!(nested.to_string(), "(a ? 1 : 0) ? (b ? 1 : 0) : b ? 0 : 1");
+    }
+
+    #[test]
+    fn cardinality_tests() {
+        let args = || -> Vec<Box<dyn Expression>> {
+            vec![Box::new(Variable::new("a")), Box::new(Variable::new("b")), Box::new(Value::new(true))]
+        };
+
+        let expr = CardinalityExpression::new(CardinalityOperator::MAJ, 0, args());
+        assert_eq!(eval_all(&expr), vec![false, true, true, true]);
+        assert_eq!(expr.to_string(), "maj(a, b, 1)");
+
+        let expr = CardinalityExpression::new(CardinalityOperator::PARITY, 0, args());
+        assert_eq!(eval_all(&expr), vec![true, false, false, true]);
+        assert_eq!(expr.to_string(), "parity(a, b, 1)");
+
+        let expr = CardinalityExpression::new(CardinalityOperator::ATLEAST, 3, args());
+        assert_eq!(eval_all(&expr), vec![false, false, false, true]);
+        assert_eq!(expr.to_string(), "atleast(3; a, b, 1)");
+
+        let expr = CardinalityExpression::new(CardinalityOperator::ATMOST, 1, args());
+        assert_eq!(eval_all(&expr), vec![true, false, false, false]);
+        assert_eq!(expr.to_string(), "atmost(1; a, b, 1)");
+
+        let expr = CardinalityExpression::new(CardinalityOperator::EXACTLY, 2, args());
+        assert_eq!(eval_all(&expr), vec![false, true, true, false]);
+        assert_eq!(expr.to_string(), "exactly(2; a, b, 1)");
+        assert_eq!(expr.to_dump_string(), "Exactly(2;Variable(a),Variable(b),Value(1))");
+    }
+
+    #[test]
+    fn substitute_tests() {
+        let expr = BinaryExpression::new(
+            BinaryOperator::AND,
+            Box::new(Variable::new("a")),
+            Box::new(CardinalityExpression::new(CardinalityOperator::MAJ, 0, vec![Box::new(Variable::new("a")), Box::new(Variable::new("b")), Box::new(Value::new(true))])));
+        let mut bindings: BTreeMap<String, Box<dyn Expression>> = BTreeMap::new();
+        bindings.insert(String::from("a"), Box::new(BinaryExpression::new(BinaryOperator::OR, Box::new(Variable::new("b")), Box::new(Variable::new("c")))));
+        bindings.insert(String::from("b"), Box::new(Variable::new("a")));
+
+        assert_eq!(expr.substitute(&bindings).to_string(), "(b | c) & maj(b | c, a, 1)");
+        assert_eq!(expr.clone_box().to_string(), "a & maj(a, b, 1)");
+    }
+
+    #[test]
+    fn quantified_tests() {
+        // exists b. a = b is always true, forall b. a | b is true iff a is
+        let expr = QuantifiedExpression::new(Quantifier::EXISTS, "b",
+            Box::new(BinaryExpression::new(BinaryOperator::EQ, Box::new(Variable::new("a")), Box::new(Variable::new("b")))));
+        assert_eq!(eval_all(&expr), vec![true, true, true, true]);
+        assert_eq!(expr.to_string(), "exists b. a = b");
+
+        let expr = QuantifiedExpression::new(Quantifier::FORALL, "b",
+            Box::new(BinaryExpression::new(BinaryOperator::OR, Box::new(Variable::new("a")), Box::new(Variable::new("b")))));
+        assert_eq!(eval_all(&expr), vec![false, true, false, true]);
+        assert_eq!(expr.to_string(), "forall b. a | b");
+
+        let outer = BinaryExpression::new(BinaryOperator::AND, expr.clone_box(), Box::new(Variable::new("b")));
+        assert_eq!(outer.to_string(), "(forall b. a | b) & b");
+        assert_eq!(free_variables(&outer).into_iter().collect::<Vec<_>>(), vec!["a", "b"]);
+        assert_eq!(free_variables(&expr).into_iter().collect::<Vec<_>>(), vec!["a"]);
+        assert_eq!(bound_variables(&outer).into_iter().collect::<Vec<_>>(), vec!["b"]);
+    }
+
+    #[test]
+    fn quantified_substitute_avoids_capture() {
+        let expr = QuantifiedExpression::new(Quantifier::EXISTS, "b",
+            Box::new(BinaryExpression::new(BinaryOperator::AND, Box::new(Variable::new("a")), Box::new(Variable::new("b")))));
+
+        let mut bindings: BTreeMap<String, Box<dyn Expression>> = BTreeMap::new();
+        bindings.insert(String::from("b"), Box::new(Value::new(true)));
+        assert_eq!(expr.substitute(&bindings).to_string(), "exists b. a & b");
+
+        bindings.insert(String::from("a"), Box::new(Variable::new("b")));
+        assert_eq!(expr.substitute(&bindings).to_string(), "exists b_1. b & b_1");
+    }
+
+    #[test]
+    fn substitute_variable_adds_paranthesis() {
+        let and = || Box::new(BinaryExpression::new(BinaryOperator::AND, Box::new(Variable::new("x")), Box::new(Variable::new("y"))));
+        let expr = BinaryExpression::new(BinaryOperator::XOR, Box::new(Variable::new("a")), Box::new(Variable::new("c")));
+        assert_eq!(substitute_variable(&expr, "c", and().as_ref()).to_string(), "a ^ x & y");
+        let expr = UnaryExpression::new(UnaryOperator::NEG, Box::new(Variable::new("c")));
+        assert_eq!(substitute_variable(&expr, "c", and().as_ref()).to_string(), "!(x & y)");
+        let expr = QuantifiedExpression::new(Quantifier::FORALL, "x", Box::new(BinaryExpression::new(BinaryOperator::OR, Box::new(Variable::new("x")), Box::new(Variable::new("c")))));
+        assert_eq!(substitute_variable(&expr, "c", and().as_ref()).to_string(), "forall x_1. x_1 | x & y");
+        assert_eq!(substitute_variable(&expr, "x", and().as_ref()).to_string(), "forall x. x | c");
+    }
+
+    #[test]
+    fn ranges_are_kept_by_clone_and_substitute() {
+        let var = with_range(Box::new(Variable::new("a")), Some(SourceRange { pos: 1, len: 1 }));
+        let expr = with_range(Box::new(UnaryExpression::new(UnaryOperator::NEG, var)), Some(SourceRange { pos: 0, len: 2 }));
+        assert_eq!(expr.clone_box().range(), Some(SourceRange { pos: 0, len: 2 }));
+        assert_eq!(expr.clone_box().children()[0].range(), Some(SourceRange { pos: 1, len: 1 }));
+        assert_eq!(expr.substitute(&BTreeMap::new()).range(), Some(SourceRange { pos: 0, len: 2 }));
+        assert_eq!(Value::new(true).range(), None);
+    }
+
+    #[test]
+    fn find_free_occurrence_and_binding() {
+        let bound = QuantifiedExpression::new(Quantifier::EXISTS, "b", Box::new(Variable::new("b")));
+        let expr = BinaryExpression::new(BinaryOperator::AND, Box::new(bound), Box::new(Variable::new("b")));
+        assert_eq!(find_free_occurrence(&expr, "b").unwrap().to_string(), "b");
+        assert_eq!(std::ptr::eq(find_free_occurrence(&expr, "b").unwrap(), expr.right.as_ref()), true);
+        assert_eq!(find_binding(&expr, "b").unwrap().to_string(), "exists b. b");
+        assert_eq!(find_free_occurrence(expr.left.as_ref(), "b").is_none(), true);
+        assert_eq!(find_binding(&expr, "a").is_none(), true);
+    }
 }
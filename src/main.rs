@@ -1,61 +1,149 @@
-use std::cell::RefCell;
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::env;
+use std::fs;
+use std::io::{self, IsTerminal, Read};
 use std::process::exit;
 
-use crate::expression::{EvaluationContext, Expression};
-use crate::parser::parse;
-use crate::tokens::{ParseError, tokenize};
+use crate::expression::{bound_variables, EvaluationContext, Expression, find_binding, find_free_occurrence, free_variables, substitute_variable, Value};
+use crate::parser::{parse_with_options, ParseOptions};
+use crate::qbf::{from_qdimacs, Qbf};
+use crate::diagnostic::{display_width, render_diagnostic};
+use crate::simplify::{residual, simplify};
+use crate::explain::{explain_dnf, explain_equivalence, format_steps};
+use crate::rewrite::{apply_rules, Rule, Strategy};
+use crate::anf::Anf;
+use crate::basis::{Basis, size};
+use crate::crypto::Properties;
+use crate::post::{Classification, common_classes, format_classifications};
+use crate::cofactor::{boolean_difference, cofactor, essential_variables, shannon_expansion};
+use crate::tokens::{Diagnostic, ErrorCode, is_identifier, operator_symbol, Token, tokenize_all};
 
 mod tokens;
 mod expression;
 mod parser;
+mod cnf;
+mod qbf;
+mod diagnostic;
+mod simplify;
+mod explain;
+mod rewrite;
+mod cofactor;
+mod anf;
+mod crypto;
+mod post;
+mod basis;
 
 fn print_usage(app_name: &str) {
     println!("Evaluates logical expressions");
-    println!("usage: {} <expr> [<preset>...]", app_name);
+    println!("usage: {} [<option>...] <expr> [<preset>...]", app_name);
     println!("<expr>:   Is the logical expression to evaluate. An expression consists of values,");
     println!("          variables and operators");
     println!("          `0` represents a `false` value and `1` a `true` value,");
-    println!("          any sequence of alphabetic characters is interpreted as a variable name.");
+    println!("          a letter or `_` followed by letters, digits, `_` or `.` is interpreted as a");
+    println!("          variable name (e.g. `x1`, `req_valid`, `fsm.state0`); any other name can be");
+    println!("          given in double quotes, e.g. `\"bus[3]\"`.");
     println!("          The following operators are known:");
     println!("          `&` - logical and            `!`  - Logical negation");
     println!("          `|` - logical or             `=>` - Logical implication");
     println!("          `^` - logical exclusive or   `=`  - Equality");
+    println!("          `!&` - logical nand          `<=`  - Converse implication");
+    println!("          `!|` - logical nor           `!=>` - Non-implication");
+    println!("          `!^` - logical exclusive nor");
+    println!("          The Unicode operators `¬`, `∧`, `∨`, `⊕`, `→`/`⇒`, `↔`/`⇔`/`≡`, `↑`/`⊼`, `↓`/`⊽`,");
+    println!("          `⊙`, `←`/`⇐` and `↛`/`⇏` may be used instead.");
     println!("          The precedence rules are as follows (objects/operators appear first are");
-    println!("          evaluated first): value, variable, `!`, `&`, `^`, `|`, `=>`, `=` ");
+    println!("          evaluated first): value, variable, `!`, [`&`, `!&`], [`|`, `^`, `!|`, `!^`],");
+    println!("          [`=`, `=>`, `<=`, `!=>`], `? :`; operators in brackets share the same level.");
+    println!("          Operators of the same level group to the left, e.g. `a | b ^ c` is");
+    println!("          `(a | b) ^ c`, except for `=>`: `a => b => c` is `a => (b => c)`. A warning");
+    println!("          is printed for such chains if the grouping matters.");
+    println!("          `s ? a : b` or `ite(s, a, b)` evaluates to `a` if `s` is true, otherwise to `b`.");
+    println!("          The following functions count how many of their arguments are true:");
+    println!("          `maj(a, b, ...)`        - more than half of the arguments are true");
+    println!("          `parity(a, b, ...)`     - an odd number of arguments is true");
+    println!("          `atleast(k; a, b, ...)` - at least `k` arguments are true");
+    println!("          `atmost(k; a, b, ...)`  - at most `k` arguments are true");
+    println!("          `exactly(k; a, b, ...)` - exactly `k` arguments are true");
+    println!("          `exists x. e` and `forall x. e` (or `∃x. e` and `∀x. e`) quantify `x` in `e`,");
+    println!("          several variables may be given, e.g. `forall x, y. x & y => exists z. z`. The");
+    println!("          quantified expression extends as far to the right as possible. The truth");
+    println!("          table contains columns for the free variables only.");
+    println!("          Own functions can be defined in front of the expression, each definition is");
+    println!("          terminated by `;`, e.g. `def sum(a, b, c) = a ^ b ^ c; sum(x, y, 1)`.");
+    println!("          The expression may span several lines; `#` starts a comment up to the end");
+    println!("          of the line, `/* ... */` encloses a block comment.");
     println!("          it is possible to influence the precedence using paranthesis. Examples:");
     println!("          `a&b`,  `(abc | !def) ^ (!abc & def)` `(a=0) & (b=1)`");
     println!("<preset>: A preset predefines the value of a variable when evaluation the");
     println!("          expression. The syntax of a preset is `[+-]<var>`, whereas `-var` means");
     println!("          to preset the variable with `false` (or `0`) and `+var` means to preset");
     println!("          the variable with `true` (or `1`).");
+    println!("<option>: By default a truth table is printed, the following options change this:");
+    println!("          `--qbf`                 decides the expression with a QBF solver; free");
+    println!("                                  variables are existentially quantified, if the");
+    println!("                                  expression is true, values for them are printed");
+    println!("          `--to-qdimacs`          prints the expression in QDIMACS format");
+    println!("          `--simplify`            prints the expression simplified by the laws of");
+    println!("                                  Boolean algebra, e.g. `a & 1 | a & !a` as `a`");
+    println!("          `--subst <var>=<expr>`  replaces the variable by the expression before");
+    println!("                                  anything else, e.g. `--subst 'c=x&y'`; several");
+    println!("                                  substitutions are applied in the given order");
+    println!("          `--residual`            prints the expression with the presets substituted");
+    println!("                                  and simplified, i.e. the expression over the");
+    println!("                                  remaining variables");
+    println!("          `--cofactor <var>=<v>`  prints the simplified expression with the variable");
+    println!("                                  fixed to the value `0` or `1`, e.g. `--cofactor x=1`");
+    println!("          `--diff <var>`          prints the Boolean difference by the variable, i.e.");
+    println!("                                  when a change of the variable changes the result");
+    println!("          `--shannon <vars>`      prints the Shannon expansion by the comma separated");
+    println!("                                  variables, e.g. `--shannon x,y`");
+    println!("          `--essential`           prints the variables the expression depends on");
+    println!("                                  (essential) and the others (redundant)");
+    println!("          `--drop-redundant`      leaves the variables the expression does not");
+    println!("                                  depend on out of the truth table");
+    println!("          `--anf`                 prints the algebraic normal form, an XOR of");
+    println!("                                  AND-monomials, and its degree");
+    println!("          `--reed-muller <vars>`  prints the Reed-Muller form in which the comma");
+    println!("                                  separated variables occur negated");
+    println!("          `--crypto`              prints the Walsh spectrum, nonlinearity, balancedness,");
+    println!("                                  correlation immunity, resiliency, algebraic degree");
+    println!("                                  and avalanche properties; `--json` prints them as JSON");
+    println!("          `--classify`            tells whether the expression is 0-preserving,");
+    println!("                                  1-preserving, monotone, self-dual and affine");
+    println!("          `--complete`            tells whether the expressions given instead of");
+    println!("                                  presets are functionally complete; an operator");
+    println!("                                  stands for its function, e.g. `--complete '=>' 0`");
+    println!("          `--basis <basis>`       rewrites the expression using only the operators of");
+    println!("                                  the basis `nand`, `nor`, `not-and` or `not-imp`");
+    println!("          `--explain`             prints the steps that rewrite the expression into a");
+    println!("                                  disjunctive normal form, naming the law of each step");
+    println!("          `--explain-to <expr>`   prints the steps that show the expression is equal");
+    println!("                                  to `<expr>`");
+    println!("          `--rule <rule>`         rewrites the expression by the rule, e.g.");
+    println!("                                  `--rule '?x & (?x | ?y) -> ?x'`; `?x` matches any");
+    println!("                                  subexpression, the option can be repeated. The");
+    println!("                                  rules that fired are printed after the result");
+    println!("          `--strategy <strategy>` rewrites `innermost` (default) or `outermost`");
+    println!("                                  subexpressions first");
+    println!("          `--max-steps <n>`       stops rewriting after `<n>` steps (default 1000)");
+    println!("          `--file <file>`         reads the expression from a file, `-` denotes the");
+    println!("                                  standard input");
+    println!("          `--no-color`            prints error messages without colours");
+    println!("          `--assoc <op>:<side>`   groups chains of the operator `<op>` to the `left`");
+    println!("                                  or `right`, e.g. `--assoc '=>:left'`");
+    println!("          `--chain-eq`            reads `a = b = c` as all operands being equal,");
+    println!("                                  i.e. `(a = b) & (b = c)`");
+    println!("          `--from-qdimacs <file>` reads the expression from a QDIMACS file instead");
+    println!("                                  of taking it from the command line");
+    println!("          Quantifiers below `=`, `^`, `!^`, `? :` and functions cannot be moved to");
+    println!("          the front of the expression and are not supported by these options.");
 }
 
 fn print_err(app_name: &str, message: &str) {
     eprintln!("*** error {}: {}", app_name, message);
 }
 
-fn print_parse_err(app_name: &str, expr: &str, err: ParseError) {
-    print_err(app_name, format!("parse error in '{}'", expr).as_str());
-    if err.len > 0 {
-        eprint_chars(28 + app_name.len() + err.pos, ' ');
-        eprint_chars(err.len, '~');
-        println!();
-    }
-    eprint_chars(28 + app_name.len() + err.pos + (err.len / 2), ' ');
-    eprintln!("|");
-    eprint_chars(28 + app_name.len() + err.pos - err.message.len() / 2, ' ');
-    eprintln!("{}", err.message);
-}
-
-fn eprint_chars(count: usize, ch: char) -> () {
-    for _ in 0..count {
-        eprint!("{}", ch);
-    }
-}
-
-fn print_table_header(ctxt: &EvaluationContext) -> () {
+fn print_table_header(ctxt: &EvaluationContext) {
     print!("|");
     for var in &ctxt.variables {
         print!(" {} |", var);
@@ -64,51 +152,343 @@ fn print_table_header(ctxt: &EvaluationContext) -> () {
 
     print!("+");
     for var in &ctxt.variables {
-        print_chars(var.len() + 2, '-');
+        print_chars(display_width(var) + 2, '-');
         print!("+");
     }
     println!("+---+");
 }
 
-fn print_table_result(ctxt: &EvaluationContext, result: bool) -> () {
+fn print_table_result(ctxt: &EvaluationContext, result: bool) {
     print!("|");
     for var in &ctxt.variables {
-        print_chars(var.len(), ' ');
+        print_chars(display_width(var), ' ');
         print!("{} |", if ctxt.get(var.as_str()) { '1' } else { '0' });
     }
     println!("| {} |", if result { '1' } else { '0' });
 }
 
-fn print_chars(count: usize, ch: char) -> () {
+fn print_chars(count: usize, ch: char) {
     for _ in 0..count {
         print!("{}", ch);
     }
 }
 
 
-fn parse_expr(str: &str) -> Result<Box<dyn Expression>, ParseError> {
-    match tokenize(str) {
-        Ok(tokens) => {
-            match parse(&tokens) {
-                Err(err) => Err(err),
-                Ok(expr) => Ok(expr)
-            }
+fn parse_expr(str: &str, options: &ParseOptions, warnings: &mut Vec<Diagnostic>) -> Result<Box<dyn Expression>, Vec<Diagnostic>> {
+    let (tokens, mut errors) = tokenize_all(str);
+
+    // Syntax errors are only searched if the tokenizer could recover from all errors
+    if !tokens.is_empty() && errors.iter().all(|err| err.help.is_some()) {
+        match parse_with_options(&tokens, options, warnings) {
+            Ok(expr) if errors.is_empty() => return Ok(expr),
+            Ok(_) => {}
+            Err(parse_errors) => errors.extend(parse_errors)
         }
-        Err(err) => Err(err)
     }
+    errors.sort_by_key(|err| err.error.pos);
+    Err(errors)
 }
 
-fn collect_variables(expr: &dyn Expression) -> BTreeSet<String> {
-    let mut variables: BTreeSet<String> = BTreeSet::new();
-    let names = RefCell::new(&mut variables);
-    expr.traverse(&|e| {
-        if let Some(var) = e.as_variable() {
-            names.borrow_mut().insert(var.name.clone());
+/// Returns the free variables, a warning is printed for variables that are also bound; it points
+/// into the source given as name and text, if any.
+fn collect_variables(app_name: &str, expr: &dyn Expression, source: Option<&(String, String)>, colour: bool) -> BTreeSet<String> {
+    let variables = free_variables(expr);
+    for var in bound_variables(expr).intersection(&variables) {
+        let message = format!("variable '{}' is used both free and bound", var);
+        let free = find_free_occurrence(expr, var).and_then(|occurrence| occurrence.range());
+        let bound = find_binding(expr, var).and_then(|binding| binding.range);
+        match (source, free, bound) {
+            (Some((name, str)), Some(free), Some(bound)) => {
+                let mut diagnostic = free.diagnostic(ErrorCode::FreeAndBound, &message);
+                diagnostic.labels.push(bound.label("bound here"));
+                eprint!("{}", render_diagnostic(name, str, &diagnostic, colour));
+            }
+            _ => eprintln!("warning {}: {}", app_name, message)
         }
-    });
+    }
     variables
 }
 
+enum Command {
+    Table,
+    Qbf,
+    ToQdimacs,
+    Simplify,
+    Residual,
+    Explain(Option<String>),
+    Rewrite,
+    Cofactor(String, bool),
+    Difference(String),
+    Shannon(Vec<String>),
+    Essential,
+    Anf(BTreeSet<String>),
+    Crypto,
+    Classify,
+    Complete,
+    Basis(Basis),
+}
+
+struct Options {
+    command: Command,
+    expr: Option<String>,
+    file: Option<String>,
+    qdimacs_file: Option<String>,
+    colour: bool,
+    drop_redundant: bool,
+    json: bool,
+    parse_options: ParseOptions,
+    substitutions: Vec<(String, String)>,
+    rules: Vec<String>,
+    strategy: Strategy,
+    max_steps: usize,
+    presets: Vec<String>,
+    /// The functions to check with `--complete`
+    functions: Vec<String>,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        command: Command::Table,
+        expr: None,
+        file: None,
+        qdimacs_file: None,
+        colour: io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none(),
+        drop_redundant: false,
+        json: false,
+        parse_options: ParseOptions::default(),
+        substitutions: Vec::new(),
+        rules: Vec::new(),
+        strategy: Strategy::Innermost,
+        max_steps: 1000,
+        presets: Vec::new(),
+        functions: Vec::new(),
+    };
+    let mut i = 0;
+    while i < args.len() {
+        let arg = &args[i];
+        match arg.as_str() {
+            "--qbf" | "--to-qdimacs" | "--simplify" | "--residual" | "--essential" | "--crypto" | "--classify" | "--complete" => {
+                if !matches!(options.command, Command::Table) {
+                    return Err(format!("option '{}' cannot be combined with another command", arg));
+                }
+                options.command = match arg.as_str() {
+                    "--qbf" => Command::Qbf,
+                    "--to-qdimacs" => Command::ToQdimacs,
+                    "--residual" => Command::Residual,
+                    "--essential" => Command::Essential,
+                    "--crypto" => Command::Crypto,
+                    "--classify" => Command::Classify,
+                    "--complete" => Command::Complete,
+                    _ => Command::Simplify
+                };
+            }
+            "--explain" | "--explain-to" => {
+                if !matches!(options.command, Command::Table) {
+                    return Err(format!("option '{}' cannot be combined with another command", arg));
+                }
+                let target = if arg == "--explain-to" {
+                    i += 1;
+                    match args.get(i) {
+                        Some(target) => Some(target.clone()),
+                        None => return Err(String::from("option '--explain-to' expects an expression"))
+                    }
+                } else {
+                    None
+                };
+                options.command = Command::Explain(target);
+            }
+            "--cofactor" | "--diff" | "--shannon" => {
+                if !matches!(options.command, Command::Table) {
+                    return Err(format!("option '{}' cannot be combined with another command", arg));
+                }
+                i += 1;
+                let setting = args.get(i).map(|setting| setting.as_str()).unwrap_or("");
+                options.command = match arg.as_str() {
+                    "--cofactor" => match setting.split_once('=') {
+                        Some((var, "0" | "1")) if is_identifier(var) => Command::Cofactor(var.to_string(), setting.ends_with('1')),
+                        _ => return Err(String::from("option '--cofactor' expects a variable and value like 'x=1'"))
+                    },
+                    "--diff" if is_identifier(setting) => Command::Difference(setting.to_string()),
+                    "--shannon" if setting.split(',').all(is_identifier) => Command::Shannon(setting.split(',').map(String::from).collect()),
+                    _ => return Err(format!("option '{}' expects a variable", arg))
+                };
+            }
+            "--anf" | "--reed-muller" => {
+                if !matches!(options.command, Command::Table) {
+                    return Err(format!("option '{}' cannot be combined with another command", arg));
+                }
+                let mut negated = BTreeSet::new();
+                if arg == "--reed-muller" {
+                    i += 1;
+                    let vars = args.get(i).map(|vars| vars.as_str()).unwrap_or("-");
+                    if !vars.is_empty() && !vars.split(',').all(is_identifier) {
+                        return Err(String::from("option '--reed-muller' expects the negated variables like 'a,b'"));
+                    }
+                    negated.extend(vars.split(',').filter(|var| !var.is_empty()).map(String::from));
+                }
+                options.command = Command::Anf(negated);
+            }
+            "--basis" => {
+                if !matches!(options.command, Command::Table) {
+                    return Err(format!("option '{}' cannot be combined with another command", arg));
+                }
+                i += 1;
+                match args.get(i).and_then(|name| Basis::from_name(name)) {
+                    Some(basis) => options.command = Command::Basis(basis),
+                    None => return Err(String::from("option '--basis' expects one of 'nand', 'nor', 'not-and' or 'not-imp'"))
+                }
+            }
+            "--subst" => {
+                i += 1;
+                let substitution = args.get(i).and_then(|setting| setting.split_once('='))
+                    .map(|(var, replacement)| (var.trim().to_string(), replacement.to_string()))
+                    .filter(|(var, _)| is_identifier(var));
+                match substitution {
+                    Some(substitution) => options.substitutions.push(substitution),
+                    None => return Err(String::from("option '--subst' expects a substitution like 'c=x&y'"))
+                }
+            }
+            "--rule" => {
+                if !matches!(options.command, Command::Table | Command::Rewrite) {
+                    return Err(String::from("option '--rule' cannot be combined with another command"));
+                }
+                i += 1;
+                match args.get(i) {
+                    Some(rule) => options.rules.push(rule.clone()),
+                    None => return Err(String::from("option '--rule' expects a rule like '?x & ?x -> ?x'"))
+                }
+                options.command = Command::Rewrite;
+            }
+            "--strategy" => {
+                i += 1;
+                options.strategy = match args.get(i).map(|strategy| strategy.as_str()) {
+                    Some("innermost") => Strategy::Innermost,
+                    Some("outermost") => Strategy::Outermost,
+                    _ => return Err(String::from("option '--strategy' expects 'innermost' or 'outermost'"))
+                };
+            }
+            "--max-steps" => {
+                i += 1;
+                options.max_steps = match args.get(i).and_then(|steps| steps.parse().ok()) {
+                    Some(steps) => steps,
+                    None => return Err(String::from("option '--max-steps' expects a number"))
+                };
+            }
+            "--file" | "--from-qdimacs" => {
+                i += 1;
+                let file = match args.get(i) {
+                    Some(file) => file.clone(),
+                    None => return Err(format!("option '{}' expects a file name", arg))
+                };
+                if arg == "--file" { options.file = Some(file) } else { options.qdimacs_file = Some(file) }
+            }
+            "--no-color" => options.colour = false,
+            "--drop-redundant" => options.drop_redundant = true,
+            "--json" => options.json = true,
+            "--chain-eq" => options.parse_options.chained_equivalence = true,
+            "--assoc" => {
+                i += 1;
+                match args.get(i) {
+                    Some(setting) => options.parse_options.set_associativity(setting)?,
+                    None => return Err(String::from("option '--assoc' expects a setting like '=>:left'"))
+                }
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option '{}'", arg)),
+            _ if options.expr.is_none() && options.file.is_none() && options.qdimacs_file.is_none() => options.expr = Some(arg.clone()),
+            _ => options.presets.push(arg.clone())
+        }
+        i += 1;
+    }
+    if let Command::Complete = options.command {
+        if options.file.is_some() || options.qdimacs_file.is_some() {
+            return Err(String::from("option '--complete' cannot be combined with reading a file"));
+        }
+        // The arguments are the functions to check, there are no presets
+        options.functions = options.expr.take().into_iter().chain(options.presets.drain(..)).collect();
+        if options.functions.is_empty() {
+            return Err(String::from("option '--complete' expects the functions to check"));
+        }
+        return Ok(options);
+    }
+    if options.expr.is_none() && options.file.is_none() && options.qdimacs_file.is_none() {
+        return Err(String::from("no expression given"));
+    }
+    if options.json && !matches!(options.command, Command::Crypto) {
+        return Err(String::from("option '--json' is only supported together with '--crypto'"));
+    }
+    Ok(options)
+}
+
+/// Reads the expression from the given file, `-` denotes the standard input.
+fn read_source(file: &str) -> Result<String, String> {
+    let mut str = String::new();
+    let result = if file == "-" { io::stdin().read_to_string(&mut str).map(|_| str) } else { fs::read_to_string(file) };
+    result.map_err(|err| err.to_string())
+}
+
+/// Returns the values of the preset variables.
+fn preset_values(ctx: &EvaluationContext) -> BTreeMap<String, bool> {
+    ctx.variables.difference(&ctx.not_preset).map(|var| (var.clone(), ctx.get(var))).collect()
+}
+
+/// Returns the expression with the preset variables replaced by their values.
+fn apply_presets(expr: &dyn Expression, ctx: &EvaluationContext) -> Box<dyn Expression> {
+    let bindings: BTreeMap<String, Box<dyn Expression>> = preset_values(ctx).into_iter()
+        .map(|(var, value)| (var, Box::new(Value::new(value)) as Box<dyn Expression>))
+        .collect();
+    expr.substitute(&bindings)
+}
+
+/// Parses the expression, prints the warnings and exits if there are errors.
+fn parse_or_exit(name: &str, str: &str, options: &Options) -> Box<dyn Expression> {
+    let mut warnings = Vec::new();
+    let result = parse_expr(str, &options.parse_options, &mut warnings);
+    for warning in &warnings {
+        eprint!("{}", render_diagnostic(name, str, warning, options.colour));
+    }
+    match result {
+        Ok(expr) => expr,
+        Err(errors) => {
+            let rendered: Vec<String> = errors.iter().map(|err| render_diagnostic(name, str, err, options.colour)).collect();
+            eprint!("{}", rendered.join("\n"));
+            if errors.len() > 1 {
+                eprintln!("\nerror: aborting due to {} errors", errors.len());
+            }
+            exit(1);
+        }
+    }
+}
+
+/// Classifies the expressions given on the command line and tells whether they are functionally
+/// complete; an operator stands for the function it computes, e.g. `=>` for `a => b`.
+fn check_completeness(app_name: &str, options: &Options) {
+    let mut functions = Vec::new();
+    for arg in &options.functions {
+        let str = match tokenize_all(arg).0.as_slice() {
+            [Token::Operator(_, op)] if operator_symbol(op) == "!" => String::from("!a"),
+            [Token::Operator(_, op)] if op == "?" => String::from("a ? b : c"),
+            [Token::Operator(_, op)] => format!("a {} b", operator_symbol(op)),
+            _ => arg.clone()
+        };
+        let expr = parse_or_exit("<expr>", &str, options);
+        match Classification::from_expression(expr.as_ref()) {
+            Ok(classification) => functions.push((expr.to_string(), classification)),
+            Err(message) => {
+                print_err(app_name, &message);
+                exit(1);
+            }
+        }
+    }
+    let classifications: Vec<Classification> = functions.iter().map(|(_, classification)| *classification).collect();
+    print!("{}", format_classifications(&functions));
+    let common = common_classes(&classifications);
+    if common.is_empty() {
+        println!("complete: yes");
+    } else {
+        println!("complete: no, all functions are {}", common.join(", "));
+    }
+}
+
 fn main() {
     let args: Vec<String> = env::args().collect();
     let app_name = if let Some(index) = args[0].rfind("/") { &args[0][(index + 1)..] } else { &args[0] };
@@ -116,19 +496,78 @@ fn main() {
         print_usage(app_name);
         exit(1);
     }
-
-    // Parse expression
-    let expr = match parse_expr(&args[1]) {
-        Ok(expr) => expr,
-        Err(err) => {
-            print_parse_err(app_name, &args[1], err);
+    let options = match parse_args(&args[1..]) {
+        Ok(options) => options,
+        Err(message) => {
+            print_err(app_name, &message);
             exit(1);
         }
     };
-    let mut ctx = EvaluationContext::new(collect_variables(expr.as_ref()));
+
+    if let Command::Complete = options.command {
+        check_completeness(app_name, &options);
+        return;
+    }
+
+    // Parse expression, the source is kept as name and text for diagnostics
+    let mut source = None;
+    let expr = if let Some(file) = &options.qdimacs_file {
+        let result = fs::read_to_string(file).map_err(|err| err.to_string()).and_then(|str| from_qdimacs(&str));
+        match result {
+            Ok(expr) => expr,
+            Err(message) => {
+                print_err(app_name, format!("cannot read '{}': {}", file, message).as_str());
+                exit(1);
+            }
+        }
+    } else {
+        let (name, str) = match &options.file {
+            Some(file) => match read_source(file) {
+                Ok(str) => (if file == "-" { "<stdin>" } else { file.as_str() }, str),
+                Err(message) => {
+                    print_err(app_name, format!("cannot read '{}': {}", file, message).as_str());
+                    exit(1);
+                }
+            },
+            None => ("<expr>", options.expr.clone().unwrap())
+        };
+        let expr = parse_or_exit(name, &str, &options);
+        source = Some((name.to_string(), str));
+        expr
+    };
+
+    // Substitute variables, the source does not match the result anymore
+    let mut expr = expr;
+    for (var, replacement) in &options.substitutions {
+        if !free_variables(expr.as_ref()).contains(var) {
+            eprintln!("warning {}: variable '{}' to substitute does not occur", app_name, var);
+        }
+        expr = substitute_variable(expr.as_ref(), var, parse_or_exit("<subst>", replacement, &options).as_ref());
+        source = None;
+    }
+
+    // Variables the expression does not depend on are fixed, so they are no table columns
+    if options.drop_redundant {
+        let essential = match essential_variables(expr.as_ref()) {
+            Ok(essential) => essential,
+            Err(message) => {
+                print_err(app_name, &message);
+                exit(1);
+            }
+        };
+        let bindings: BTreeMap<String, Box<dyn Expression>> = free_variables(expr.as_ref()).difference(&essential)
+            .map(|var| (var.clone(), Box::new(Value::new(false)) as Box<dyn Expression>))
+            .collect();
+        if !bindings.is_empty() {
+            let names: Vec<&str> = bindings.keys().map(|var| var.as_str()).collect();
+            eprintln!("warning {}: dropping the redundant variables {}", app_name, names.join(", "));
+            expr = expr.substitute(&bindings);
+        }
+    }
+    let mut ctx = EvaluationContext::new(collect_variables(app_name, expr.as_ref(), source.as_ref(), options.colour));
 
     // parse presets
-    for arg in &args[2..] {
+    for arg in &options.presets {
         if arg.starts_with("-") || arg.starts_with("+") {
             let var = &arg[1..];
             let val = arg.starts_with("+");
@@ -141,11 +580,147 @@ fn main() {
         }
     }
 
-    let count: u128 = 1 << ctx.not_preset.len();
-    print_table_header(&ctx);
-    for i in 0..count {
-        ctx.set_not_presets(i);
-        print_table_result(&ctx, expr.eval(&ctx));
+    match &options.command {
+        Command::Table => {
+            let count: u128 = 1 << ctx.not_preset.len();
+            print_table_header(&ctx);
+            for i in 0..count {
+                ctx.set_not_presets(i);
+                print_table_result(&ctx, expr.eval(&ctx));
+            }
+        }
+        Command::Qbf | Command::ToQdimacs => {
+            let qbf = match Qbf::from_expression(apply_presets(expr.as_ref(), &ctx).as_ref()) {
+                Ok(qbf) => qbf,
+                Err(message) => {
+                    print_err(app_name, &message);
+                    exit(1);
+                }
+            };
+            if let Command::ToQdimacs = options.command {
+                print!("{}", qbf.to_qdimacs());
+            } else if let Some(witness) = qbf.solve() {
+                println!("true");
+                for (var, value) in witness {
+                    println!("{} = {}", var, if value { '1' } else { '0' });
+                }
+            } else {
+                println!("false");
+            }
+        }
+        Command::Simplify => {
+            println!("{}", simplify(apply_presets(expr.as_ref(), &ctx).as_ref()).to_string());
+        }
+        Command::Residual => {
+            let result = residual(expr.as_ref(), &preset_values(&ctx));
+            println!("{}", result.to_string());
+            let used = free_variables(result.as_ref());
+            let unused: Vec<&str> = ctx.not_preset.iter().filter(|var| !used.contains(*var)).map(|var| var.as_str()).collect();
+            if !unused.is_empty() {
+                println!("# no longer depends on {}", unused.join(", "));
+            }
+        }
+        Command::Cofactor(var, value) => {
+            println!("{}", cofactor(apply_presets(expr.as_ref(), &ctx).as_ref(), var, *value).to_string());
+        }
+        Command::Difference(var) => {
+            println!("{}", boolean_difference(apply_presets(expr.as_ref(), &ctx).as_ref(), var).to_string());
+        }
+        Command::Shannon(vars) => {
+            println!("{}", shannon_expansion(apply_presets(expr.as_ref(), &ctx).as_ref(), vars).to_string());
+        }
+        Command::Essential => {
+            let expr = apply_presets(expr.as_ref(), &ctx);
+            match essential_variables(expr.as_ref()) {
+                Ok(essential) => {
+                    let redundant: Vec<&str> = ctx.not_preset.iter().filter(|var| !essential.contains(*var)).map(|var| var.as_str()).collect();
+                    println!("essential: {}", essential.iter().map(|var| var.as_str()).collect::<Vec<_>>().join(", "));
+                    println!("redundant: {}", redundant.join(", "));
+                }
+                Err(message) => {
+                    print_err(app_name, &message);
+                    exit(1);
+                }
+            }
+        }
+        Command::Anf(negated) => {
+            match Anf::with_polarity(apply_presets(expr.as_ref(), &ctx).as_ref(), negated) {
+                Ok(anf) => {
+                    println!("{}", anf.to_expression().to_string());
+                    println!("# degree {}", anf.degree());
+                }
+                Err(message) => {
+                    print_err(app_name, &message);
+                    exit(1);
+                }
+            }
+        }
+        Command::Crypto => {
+            match Properties::from_expression(apply_presets(expr.as_ref(), &ctx).as_ref()) {
+                Ok(properties) => print!("{}", if options.json { properties.to_json() } else { properties.to_table() }),
+                Err(message) => {
+                    print_err(app_name, &message);
+                    exit(1);
+                }
+            }
+        }
+        Command::Classify => {
+            let expr = apply_presets(expr.as_ref(), &ctx);
+            match Classification::from_expression(expr.as_ref()) {
+                Ok(classification) => print!("{}", format_classifications(&[(expr.to_string(), classification)])),
+                Err(message) => {
+                    print_err(app_name, &message);
+                    exit(1);
+                }
+            }
+        }
+        Command::Basis(basis) => {
+            let result = basis.rewrite(apply_presets(expr.as_ref(), &ctx).as_ref());
+            println!("{}", result.to_string());
+            println!("# size {}", size(result.as_ref()));
+        }
+        Command::Complete => {} // handled before the expression is parsed
+        Command::Rewrite => {
+            let mut rules = Vec::new();
+            for text in &options.rules {
+                let mut warnings = Vec::new();
+                let result = Rule::parse(text, &options.parse_options, &mut warnings);
+                for warning in &warnings {
+                    eprint!("{}", render_diagnostic("<rule>", text, warning, options.colour));
+                }
+                match result {
+                    Ok(rule) => rules.push(rule),
+                    Err(errors) => {
+                        let rendered: Vec<String> = errors.iter().map(|err| render_diagnostic("<rule>", text, err, options.colour)).collect();
+                        eprint!("{}", rendered.join("\n"));
+                        exit(1);
+                    }
+                }
+            }
+            let result = apply_rules(apply_presets(expr.as_ref(), &ctx).as_ref(), &rules, options.strategy, options.max_steps);
+            if !result.complete {
+                eprintln!("warning {}: stopped after {} steps, further rules apply", app_name, result.steps);
+            }
+            println!("{}", result.expr.to_string());
+            for (rule, count) in rules.iter().zip(&result.fired) {
+                if *count > 0 {
+                    println!("# `{}` fired {} time{}", rule.text, count, if *count == 1 { "" } else { "s" });
+                }
+            }
+        }
+        Command::Explain(target) => {
+            let expr = apply_presets(expr.as_ref(), &ctx);
+            let result = match target {
+                Some(target) => explain_equivalence(expr.as_ref(), apply_presets(parse_or_exit("<target>", target, &options).as_ref(), &ctx).as_ref()),
+                None => explain_dnf(expr.as_ref())
+            };
+            match result {
+                Ok(steps) => print!("{}", format_steps(expr.as_ref(), &steps)),
+                Err(message) => {
+                    print_err(app_name, &message);
+                    exit(1);
+                }
+            }
+        }
     }
 }
-
@@ -1,381 +1,1513 @@
+use std::collections::BTreeMap;
+
 use crate::expression;
-use crate::expression::{BinaryExpression, BinaryOperator, UnaryExpression, UnaryOperator};
-use crate::tokens::{ParseError, Token, token_len, token_name, token_pos};
+use crate::expression::{with_range, BinaryExpression, BinaryOperator, CardinalityExpression, CardinalityOperator, ConditionalExpression, QuantifiedExpression, Quantifier, UnaryExpression, UnaryOperator};
+use crate::tokens::{Diagnostic, ErrorCode, Label, operator_symbol, ParseError, SourceRange, Token, token_len, token_name, token_pos};
+
+/// A user defined function, declared via `def name(params) = body;`
+pub struct Function {
+    params: Vec<String>,
+    body: Box<dyn expression::Expression>,
+}
+
+pub type Functions = BTreeMap<String, Function>;
+
+const BUILTIN_FUNCTIONS: &[&str] = &["ite", "maj", "parity", "atleast", "atmost", "exactly"];
+
+/// The side to which chains of operators of the same precedence are grouped, e.g. `a => b => c`
+/// is `a => (b => c)` since `=>` is right associative.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Associativity {
+    Left,
+    Right,
+}
 
+/// Options changing how chains of binary operators are read.
+#[derive(Clone, Default)]
+pub struct ParseOptions {
+    /// Overrides the default associativity of operators, keyed by their ASCII symbol
+    pub associativity: BTreeMap<String, Associativity>,
+    /// Reads `a = b = c` as "all operands are equal", i.e. `(a = b) & (b = c)`
+    pub chained_equivalence: bool,
+}
+
+impl ParseOptions {
+    /// Overrides the associativity of an operator as given by a setting like `=>:left`.
+    pub fn set_associativity(&mut self, setting: &str) -> Result<(), String> {
+        let (operator, associativity) = match setting.rsplit_once(':') {
+            Some((operator, "left")) => (operator, Associativity::Left),
+            Some((operator, "right")) => (operator, Associativity::Right),
+            _ => return Err(format!("invalid associativity '{}', expected e.g. '=>:left'", setting))
+        };
+        let operator = operator_symbol(operator);
+        if !is_binary_operator(operator) {
+            return Err(format!("'{}' is not a binary operator", operator));
+        }
+        self.associativity.insert(operator.to_string(), associativity);
+        Ok(())
+    }
+
+    fn get_associativity(&self, operator: &str) -> Associativity {
+        match self.associativity.get(operator) {
+            _ if operator == "=" && self.chained_equivalence => Associativity::Left,
+            Some(associativity) => *associativity,
+            None => get_associativity(operator)
+        }
+    }
+}
+
+/// Parses an expression that might be preceded by function definitions, each terminated by `;`.
+#[cfg(test)]
 pub fn parse(tokens: &[Token]) -> Result<Box<dyn expression::Expression>, ParseError> {
-    match tokens.len() {
-        0 => Err(ParseError {
-            pos: 0,
+    parse_all(tokens).map_err(|mut errors| errors.remove(0).error)
+}
+
+/// Like `parse`, but continues after an error to report as many errors as possible at once; the
+/// first error is the one `parse` returns.
+#[cfg(test)]
+pub fn parse_all(tokens: &[Token]) -> Result<Box<dyn expression::Expression>, Vec<Diagnostic>> {
+    parse_with_options(tokens, &ParseOptions::default(), &mut Vec::new())
+}
+
+/// Like `parse_all`, but reads chains of operators according to the options; warnings, e.g. about
+/// ambiguous chains, are added to `warnings`.
+pub fn parse_with_options(tokens: &[Token], options: &ParseOptions, warnings: &mut Vec<Diagnostic>) -> Result<Box<dyn expression::Expression>, Vec<Diagnostic>> {
+    let mut errors = Vec::new();
+    let mut functions = Functions::new();
+    let mut rest = tokens;
+    while let Some(pos) = find_top_level_semicolon(rest) {
+        if !is_definition(&rest[..pos]) {
+            errors.push(ParseError {
+                code: ErrorCode::UnexpectedToken,
+                pos: token_pos(&rest[pos]),
+                len: 1,
+                message: String::from("\";\" is only allowed after function definitions"),
+            }.into());
+        } else if let Err(definition_errors) = parse_definition(&rest[..pos], &mut functions, options, warnings) {
+            errors.extend(definition_errors);
+            declare_broken_definition(&rest[..pos], &mut functions);
+        }
+        rest = &rest[(pos + 1)..];
+    }
+
+    let mut result = None;
+    if rest.is_empty() && !tokens.is_empty() {
+        let last = &tokens[tokens.len() - 1];
+        errors.push(ParseError {
+            code: ErrorCode::MissingInput,
+            pos: token_pos(last) + token_len(last),
             len: 0,
-            message: String::from("Missing input"),
-        }),
+            message: String::from("expression expected after function definitions"),
+        }.into());
+    } else if is_definition(rest) {
+        errors.push(ParseError {
+            code: ErrorCode::InvalidDefinition,
+            pos: token_pos(&rest[rest.len() - 1]) + token_len(&rest[rest.len() - 1]),
+            len: 0,
+            message: String::from("\";\" expected after function definition"),
+        }.into());
+    } else {
+        match parse_expression(rest, &functions, options, warnings) {
+            Ok(expr) => result = Some(expr),
+            Err(expression_errors) => errors.extend(expression_errors)
+        }
+    }
 
-        1 => parse_single_token_expression(&tokens[0]),
+    match result {
+        Some(expr) if errors.is_empty() => Ok(expr),
+        _ => {
+            add_hints(tokens, &mut errors);
+            Err(errors)
+        }
+    }
+}
 
-        _ => match find_top_level_operator(tokens) {
-            Some(pos) => parse_operator_expression(tokens, pos),
-            _ => parse_paranthesis_expression(tokens),
+/// Declares the function of a definition that could not be parsed, so calls of it are not
+/// reported as unknown functions.
+fn declare_broken_definition(tokens: &[Token], functions: &mut Functions) {
+    let name = token_name(&tokens[1]);
+    if BUILTIN_FUNCTIONS.contains(&name) || functions.contains_key(name) {
+        return;
+    }
+    let mut params = Vec::new();
+    for token in tokens[2..].iter().take_while(|token| !matches!(token, Token::CloseParanthesis(_))) {
+        if let Token::Variable(_, param) | Token::QuotedVariable(_, param) = token {
+            if !params.contains(param) {
+                params.push(param.clone());
+            }
         }
     }
+    functions.insert(name.to_string(), Function { params, body: Box::new(expression::Value::new(false)) });
 }
 
-fn parse_paranthesis_expression(tokens: &[Token]) -> Result<Box<dyn expression::Expression>, ParseError> {
-    match tokens[0] {
-        Token::OpenParanthesis(_) => (),
-        _ => return Err(ParseError {
-            pos: token_pos(&tokens[1]),
-            len: token_len(&tokens[1]),
-            message: String::from("operator expected"),
-        })
+/// Operators written as words in other notations.
+const OPERATOR_WORDS: &[(&str, &str)] = &[
+    ("and", "&"),
+    ("or", "|"),
+    ("xor", "^"),
+    ("not", "!"),
+    ("implies", "=>"),
+    ("iff", "="),
+];
+
+/// Adds hints to the errors, e.g. where an unbalanced parenthesis was opened.
+fn add_hints(tokens: &[Token], errors: &mut [Diagnostic]) {
+    let mut open = Vec::new();
+    let mut unmatched_close = Vec::new();
+    for token in tokens {
+        match token {
+            Token::OpenParanthesis(pos) => open.push(*pos),
+            Token::CloseParanthesis(pos) => match open.pop() {
+                Some(_) => {}
+                None => unmatched_close.push(*pos)
+            },
+            _ => {}
+        }
     }
 
-    let mut plevel = 0;
+    for diagnostic in errors.iter_mut() {
+        let pos = diagnostic.error.pos;
+        if diagnostic.error.message == "\")\" expected" {
+            if let Some(open) = open.iter().rev().find(|open| **open < pos) {
+                diagnostic.labels.push(Label { message: String::from("unbalanced `(` opened here"), pos: *open, len: 1 });
+            }
+        }
+        if diagnostic.error.message == "operator expected" {
+            if let Some(close) = unmatched_close.iter().rev().find(|close| **close <= pos) {
+                diagnostic.labels.push(Label { message: String::from("unbalanced `)`"), pos: *close, len: 1 });
+            }
+            let index = tokens.iter().position(|token| token_pos(token) == pos);
+            let words = index.into_iter().flat_map(|index| [Some(index), index.checked_sub(1)]).flatten();
+            for index in words {
+                if let Some((_, meant)) = OPERATOR_WORDS.iter().find(|(word, _)| matches!(&tokens[index], Token::Variable(_, name) if name == word)) {
+                    diagnostic.help = Some(format!("did you mean `{}`?", meant));
+                    break;
+                }
+            }
+        }
+    }
+}
 
-    for i in 0..tokens.len() {
-        match &tokens[i] {
-            Token::OpenParanthesis(_) => plevel += 1,
-            Token::CloseParanthesis(_) => {
-                plevel -= 1;
-                if plevel < 0 && i + 1 < tokens.len() {
-                    return Err(ParseError {
-                        pos: token_pos(&tokens[i + 1]),
-                        len: token_len(&tokens[i + 1]),
-                        message: String::from("operator expected"),
-                    });
+
+fn is_definition(tokens: &[Token]) -> bool {
+    matches!(tokens, [Token::Variable(_, keyword), Token::Variable(_, _), ..] if keyword == "def")
+}
+
+fn parse_definition(tokens: &[Token], functions: &mut Functions, options: &ParseOptions, warnings: &mut Vec<Diagnostic>) -> Result<(), Vec<Diagnostic>> {
+    let name = &tokens[1];
+    if BUILTIN_FUNCTIONS.contains(&token_name(name)) || functions.contains_key(token_name(name)) {
+        return Err(vec![ParseError {
+            code: ErrorCode::InvalidDefinition,
+            pos: token_pos(name),
+            len: token_len(name),
+            message: format!("function '{}' is already defined", token_name(name)),
+        }.into()]);
+    }
+    match tokens.get(2) {
+        Some(Token::OpenParanthesis(_)) => {}
+        _ => return Err(vec![ParseError {
+            code: ErrorCode::InvalidDefinition,
+            pos: token_pos(name) + token_len(name),
+            len: 0,
+            message: String::from("\"(\" expected"),
+        }.into()])
+    }
+
+    let mut params = Vec::new();
+    let mut i = 3;
+    loop {
+        match tokens.get(i) {
+            Some(Token::Variable(pos, param)) | Some(Token::QuotedVariable(pos, param)) => {
+                if params.contains(param) {
+                    return Err(vec![ParseError {
+                        code: ErrorCode::InvalidDefinition,
+                        pos: *pos,
+                        len: token_len(&tokens[i]),
+                        message: format!("parameter '{}' declared twice", param),
+                    }.into()]);
                 }
+                params.push(param.clone());
             }
-            _ => {}
+            Some(token) => return Err(vec![ParseError {
+                code: ErrorCode::InvalidDefinition,
+                pos: token_pos(token),
+                len: token_len(token),
+                message: String::from("parameter name expected"),
+            }.into()]),
+            None => return Err(vec![ParseError {
+                code: ErrorCode::InvalidDefinition,
+                pos: token_pos(&tokens[i - 1]) + token_len(&tokens[i - 1]),
+                len: 0,
+                message: String::from("parameter name expected"),
+            }.into()])
+        }
+        match tokens.get(i + 1) {
+            Some(Token::Comma(_)) => i += 2,
+            Some(Token::CloseParanthesis(_)) => break,
+            _ => return Err(vec![ParseError {
+                code: ErrorCode::InvalidDefinition,
+                pos: token_pos(&tokens[i]) + token_len(&tokens[i]),
+                len: 0,
+                message: String::from("\",\" or \")\" expected"),
+            }.into()])
         }
     }
 
-    if plevel > 0 {
-        return Err(ParseError {
-            pos: token_pos(&tokens[tokens.len() - 1]) + token_len(&tokens[tokens.len() - 1]),
+    let assign = &tokens[i + 1];
+    match tokens.get(i + 2) {
+        Some(token) if token_name(token) == "=" => {}
+        _ => return Err(vec![ParseError {
+            code: ErrorCode::InvalidDefinition,
+            pos: token_pos(assign) + 1,
             len: 0,
-            message: String::from("\")\" expected"),
-        });
+            message: String::from("\"=\" expected"),
+        }.into()])
+    }
+    if tokens.len() == i + 3 {
+        return Err(vec![ParseError {
+            code: ErrorCode::InvalidDefinition,
+            pos: token_pos(&tokens[i + 2]) + 1,
+            len: 0,
+            message: String::from("missing function body"),
+        }.into()]);
     }
 
-    parse(&tokens[1..(tokens.len() - 1)])
+    let body = parse_expression(&tokens[(i + 3)..], functions, options, warnings)?;
+    functions.insert(token_name(name).to_string(), Function { params, body });
+    Ok(())
 }
 
-fn parse_operator_expression(tokens: &[Token], op_pos: usize) -> Result<Box<dyn expression::Expression>, ParseError> {
-    let token = &tokens[op_pos];
-    let left = if op_pos > 0 {
-        match parse(&tokens[0..op_pos]) {
-            Ok(expr) => Some(expr),
-            Err(err) => return Err(err)
-        }
-    } else {
-        None
+fn parse_expression(tokens: &[Token], functions: &Functions, options: &ParseOptions, warnings: &mut Vec<Diagnostic>) -> Result<Box<dyn expression::Expression>, Vec<Diagnostic>> {
+    if tokens.is_empty() {
+        return Err(vec![ParseError {
+            code: ErrorCode::MissingInput,
+            pos: 0,
+            len: 0,
+            message: String::from("Missing input"),
+        }.into()]);
+    }
+
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        functions,
+        options,
+        open_conditionals: 0,
+        open_paranthesis: 0,
+        in_arguments: false,
+        errors: Vec::new(),
+        warnings: Vec::new(),
     };
-    let right = if op_pos < tokens.len() - 1 {
-        match parse(&tokens[(op_pos + 1)..]) {
-            Ok(expr) => Some(expr),
-            Err(err) => return Err(err)
+    let expr = parser.parse_expression();
+    warnings.append(&mut parser.warnings);
+    if parser.errors.is_empty() { Ok(expr) } else { Err(parser.errors) }
+}
+
+/// A precedence climbing parser; it continues after an error, the erroneous part is replaced by
+/// `0` then.
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    functions: &'a Functions,
+    options: &'a ParseOptions,
+    /// The number of `?` within the current parentheses waiting for their `:`
+    open_conditionals: usize,
+    /// The number of enclosing parentheses, including those of calls
+    open_paranthesis: usize,
+    /// Whether the innermost parentheses enclose the arguments of a call
+    in_arguments: bool,
+    errors: Vec<Diagnostic>,
+    warnings: Vec<Diagnostic>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.pos)
+    }
+
+    /// Returns the symbol of the next token if it is an operator.
+    fn peek_operator(&self) -> Option<&'a str> {
+        match self.peek() {
+            Some(token @ Token::Operator(_, _)) => Some(token_name(token)),
+            _ => None
         }
-    } else {
-        None
-    };
+    }
 
-    if right.is_none() {
-        return Err(ParseError {
-            pos: token_pos(token) + token_len(token),
-            len: 0,
-            message: String::from("missing right hand side operand"),
+    /// Returns the position right behind the previous token.
+    fn end_of_previous(&self) -> usize {
+        let token = &self.tokens[self.pos - 1];
+        token_pos(token) + token_len(token)
+    }
+
+    /// Returns the range of the tokens from `start` up to the previous token.
+    fn range_from(&self, start: usize) -> Option<SourceRange> {
+        if start >= self.pos {
+            return None;
+        }
+        let pos = token_pos(&self.tokens[start]);
+        Some(SourceRange { pos, len: self.end_of_previous() - pos })
+    }
+
+    fn error(&mut self, code: ErrorCode, pos: usize, len: usize, message: &str) {
+        self.errors.push(ParseError { code, pos, len, message: String::from(message) }.into());
+    }
+
+    /// Parses a conditional expression, the lowest precedence level.
+    fn parse_expression(&mut self) -> Box<dyn expression::Expression> {
+        let start = self.pos;
+        let condition = self.parse_binary_expression();
+        if self.peek_operator() != Some("?") {
+            return condition;
+        }
+        self.pos += 1;
+        self.open_conditionals += 1;
+        let if_true = self.parse_expression();
+        self.open_conditionals -= 1;
+        let if_false = if self.peek_operator() == Some(":") {
+            self.pos += 1;
+            self.parse_expression()
+        } else {
+            self.error(ErrorCode::UnexpectedToken, self.end_of_previous(), 0, "\":\" expected");
+            placeholder()
+        };
+        with_range(Box::new(ConditionalExpression::new(condition, if_true, if_false)), self.range_from(start))
+    }
+
+    /// Parses a chain of binary operators. Pending operators are kept on a stack instead of
+    /// recursing, so long chains like `a | b | c | ...` are parsed in linear time.
+    fn parse_binary_expression(&mut self) -> Box<dyn expression::Expression> {
+        let mut operand_start = self.pos;
+        let mut operands = vec![self.parse_unary_expression()];
+        let mut operators: Vec<&Token> = Vec::new();
+        // The last operand of a chained equivalence, it is compared with the next operand
+        let mut chain = None;
+        while let Some(token) = self.peek() {
+            match token {
+                Token::Operator(_, _) if is_binary_operator(token_name(token)) => {
+                    let operator = token_name(token);
+                    while let Some(pending) = operators.last() {
+                        let pending = *pending;
+                        if get_precedence(token_name(pending)) == get_precedence(operator) {
+                            self.check_chain(pending, token);
+                        }
+                        if !self.binds_before(token_name(pending), operator) {
+                            break;
+                        }
+                        let last = match (token_name(pending), operator) {
+                            ("=", "=") if self.options.chained_equivalence => operands.last().map(|operand| operand.clone_box()),
+                            _ => None
+                        };
+                        reduce(&mut operators, &mut operands, &mut chain);
+                        if last.is_some() {
+                            chain = last;
+                        }
+                    }
+                    operators.push(token);
+                    self.pos += 1;
+                    operand_start = self.pos;
+                    operands.push(self.parse_unary_expression());
+                }
+                Token::Operator(pos, _) => match token_name(token) {
+                    "?" => break,
+                    ":" if self.open_conditionals > 0 => break,
+                    ":" => {
+                        self.error(ErrorCode::UnexpectedToken, *pos, 1, "\":\" without matching \"?\"");
+                        self.pos += 1;
+                        self.parse_expression();
+                    }
+                    "!" => {
+                        let start = token_pos(&self.tokens[operand_start]);
+                        self.error(ErrorCode::UnexpectedToken, start, self.end_of_previous() - start, "unexpected left hand side operand");
+                        operands.pop();
+                        operands.push(self.parse_unary_expression());
+                    }
+                    _ => self.operator_expected(token)
+                },
+                Token::CloseParanthesis(_) if self.open_paranthesis > 0 => break,
+                Token::Comma(_) | Token::Semicolon(_) if self.in_arguments => break,
+                _ => self.operator_expected(token)
+            }
+        }
+        while !operators.is_empty() {
+            reduce(&mut operators, &mut operands, &mut chain);
+        }
+        operands.pop().unwrap()
+    }
+
+    /// Checks whether the pending operator binds its operands before the following one.
+    fn binds_before(&self, pending: &str, following: &str) -> bool {
+        match get_precedence(pending).cmp(&get_precedence(following)) {
+            std::cmp::Ordering::Greater => true,
+            std::cmp::Ordering::Equal => self.options.get_associativity(following) == Associativity::Left,
+            std::cmp::Ordering::Less => false
+        }
+    }
+
+    /// Warns about a chain of two operators of the same precedence whose grouping matters, e.g.
+    /// `a => b => c` or `a | b ^ c`.
+    fn check_chain(&mut self, pending: &Token, following: &Token) {
+        let (first, second) = (token_name(pending), token_name(following));
+        let unambiguous = match (first, second) {
+            ("=", "=") => self.options.chained_equivalence,
+            _ => first == second && matches!(first, "&" | "|" | "^" | "!^")
+        };
+        if unambiguous {
+            return;
+        }
+        let reading = match self.options.get_associativity(second) {
+            Associativity::Left => format!("`(a {} b) {} c`", first, second),
+            Associativity::Right => format!("`a {} (b {} c)`", first, second)
+        };
+        self.warnings.push(Diagnostic {
+            error: ParseError {
+                code: ErrorCode::AmbiguousChain,
+                pos: token_pos(following),
+                len: token_len(following),
+                message: format!("ambiguous chain of `{}` and `{}`", first, second),
+            },
+            labels: vec![Label { message: String::from("chained with this operator"), pos: token_pos(pending), len: token_len(pending) }],
+            help: Some(format!("this is read as {}, add parentheses to make this explicit", reading)),
         });
     }
-    let right = right.unwrap();
-
-    if left.is_some() {
-        let left = left.unwrap();
-        match token_name(token) {
-            "!" => Err(ParseError {
-                pos: token_pos(&tokens[0]),
-                len: token_pos(&tokens[op_pos - 1]) + token_len(&tokens[op_pos - 1]),
-                message: String::from("unexpected left hand side operand"),
-            }),
-            "|" => Ok(Box::new(BinaryExpression::new(BinaryOperator::OR, left, right))),
-            "&" => Ok(Box::new(BinaryExpression::new(BinaryOperator::AND, left, right))),
-            "^" => Ok(Box::new(BinaryExpression::new(BinaryOperator::XOR, left, right))),
-            "=" => Ok(Box::new(BinaryExpression::new(BinaryOperator::EQ, left, right))),
-            "=>" => Ok(Box::new(BinaryExpression::new(BinaryOperator::IMP, left, right))),
-            _ => Err(ParseError {
-                pos: token_pos(token),
-                len: token_len(token),
-                message: String::from(format!("unknown operator '{}'", token_name(token))),
-            })
+
+    /// Reports a missing operator in front of the given token and skips to the next operator.
+    fn operator_expected(&mut self, token: &Token) {
+        self.error(ErrorCode::UnexpectedToken, token_pos(token), token_len(token), "operator expected");
+        let mut plevel = 0;
+        let start = self.pos;
+        while let Some(token) = self.peek() {
+            if plevel == 0 && self.pos > start && self.ends_operand(token) {
+                break;
+            }
+            match token {
+                Token::OpenParanthesis(_) => plevel += 1,
+                Token::CloseParanthesis(_) if plevel > 0 => plevel -= 1,
+                _ => {}
+            }
+            self.pos += 1;
         }
-    } else {
-        match token_name(token) {
-            "!" => Ok(Box::new(UnaryExpression::new(UnaryOperator::NEG, right))),
-            "|" | "&" | "^" | "=" | "=>" => Err(ParseError {
-                pos: token_pos(token),
-                len: 0,
-                message: String::from("missing left hand side operand"),
-            }),
-            _ => Err(ParseError {
-                pos: token_pos(token),
-                len: token_len(token),
-                message: String::from(format!("unknown operator '{}'", token_name(token))),
+    }
+
+    /// Checks whether the token terminates an operand, i.e. it is an operator or ends the current
+    /// parentheses or argument.
+    fn ends_operand(&self, token: &Token) -> bool {
+        match token {
+            Token::Operator(_, _) => matches!(token_name(token), "?" | ":") || is_binary_operator(token_name(token)),
+            Token::CloseParanthesis(_) => true,
+            Token::Comma(_) | Token::Semicolon(_) => self.in_arguments,
+            _ => false
+        }
+    }
+
+    fn parse_unary_expression(&mut self) -> Box<dyn expression::Expression> {
+        let start = self.pos;
+        let expr: Box<dyn expression::Expression> = if self.peek_operator() == Some("!") {
+            self.pos += 1;
+            Box::new(UnaryExpression::new(UnaryOperator::NEG, self.parse_unary_expression()))
+        } else {
+            self.parse_primary_expression()
+        };
+        with_range(expr, self.range_from(start))
+    }
+
+    /// Parses a value, variable, call, quantified expression or an expression in parentheses.
+    fn parse_primary_expression(&mut self) -> Box<dyn expression::Expression> {
+        let token = match self.peek() {
+            Some(token) => token,
+            None => {
+                self.missing_operand();
+                return placeholder();
+            }
+        };
+        match token {
+            Token::Value(_, value) => {
+                self.pos += 1;
+                Box::new(expression::Value::new(*value))
+            }
+            _ if is_quantifier(self.tokens, self.pos) => self.parse_quantified_expression(),
+            Token::Variable(_, _) if matches!(self.tokens.get(self.pos + 1), Some(Token::OpenParanthesis(_))) => self.parse_call_expression(),
+            Token::Variable(_, name) | Token::QuotedVariable(_, name) => {
+                self.pos += 1;
+                Box::new(expression::Variable::new(name))
+            }
+            Token::OpenParanthesis(_) => {
+                self.pos += 1;
+                let expr = self.parse_nested_expression(false);
+                self.expect_close_paranthesis();
+                expr
+            }
+            Token::Operator(pos, _) if token_name(token) == "?" => {
+                self.error(ErrorCode::MissingOperand, *pos, 0, "missing condition");
+                placeholder()
+            }
+            Token::Operator(pos, _) if is_binary_operator(token_name(token)) && self.tokens.get(self.pos + 1).is_some_and(starts_operand) => {
+                self.error(ErrorCode::MissingOperand, *pos, 0, "missing left hand side operand");
+                placeholder()
+            }
+            Token::CloseParanthesis(_) | Token::Comma(_) if self.pos > 0 => {
+                self.missing_operand();
+                placeholder()
+            }
+            Token::Operator(_, _) if self.pos > 0 && token_name(token) == ":" => {
+                self.missing_operand();
+                placeholder()
+            }
+            _ => {
+                self.error(ErrorCode::UnexpectedToken, token_pos(token), token_len(token), "value or variable expected");
+                self.pos += 1;
+                placeholder()
+            }
+        }
+    }
+
+    /// Reports a missing operand in front of the next token; the message depends on the token
+    /// the operand was expected after.
+    fn missing_operand(&mut self) {
+        let previous = &self.tokens[self.pos - 1];
+        let end = token_pos(previous) + token_len(previous);
+        let (code, pos, len, message) = match previous {
+            Token::Operator(_, _) => match token_name(previous) {
+                "?" => (ErrorCode::MissingOperand, end, 0, "missing then operand"),
+                ":" => (ErrorCode::MissingOperand, end, 0, "missing else operand"),
+                _ => (ErrorCode::MissingOperand, end, 0, "missing right hand side operand"),
+            },
+            Token::Dot(_) => (ErrorCode::MissingOperand, end, 0, "missing quantifier body"),
+            Token::Comma(_) | Token::OpenParanthesis(_) if self.in_arguments => {
+                let next_end = self.peek().map_or(end, |next| token_pos(next) + token_len(next));
+                (ErrorCode::MissingOperand, token_pos(previous), next_end - token_pos(previous), "empty argument")
+            }
+            _ => match self.peek() {
+                Some(next) => (ErrorCode::UnexpectedToken, token_pos(next), token_len(next), "value or variable expected"),
+                None => (ErrorCode::UnexpectedToken, end, 0, "value or variable expected"),
+            }
+        };
+        self.error(code, pos, len, message);
+    }
+
+    /// Parses the expression within parentheses; within the arguments of a call `,` and `;`
+    /// separate expressions.
+    fn parse_nested_expression(&mut self, in_arguments: bool) -> Box<dyn expression::Expression> {
+        let outer = (self.open_conditionals, self.in_arguments);
+        self.open_conditionals = 0;
+        self.in_arguments = in_arguments;
+        self.open_paranthesis += 1;
+        let expr = self.parse_expression();
+        self.open_paranthesis -= 1;
+        (self.open_conditionals, self.in_arguments) = outer;
+        expr
+    }
+
+    fn expect_close_paranthesis(&mut self) {
+        match self.peek() {
+            Some(Token::CloseParanthesis(_)) => self.pos += 1,
+            _ => self.error(ErrorCode::UnexpectedToken, self.end_of_previous(), 0, "\")\" expected")
+        }
+    }
+
+    fn parse_call_expression(&mut self) -> Box<dyn expression::Expression> {
+        let name = &self.tokens[self.pos];
+        let open = &self.tokens[self.pos + 1];
+        self.pos += 2;
+        let known_errors = self.errors.len();
+
+        let mut bound = self.parse_bound(open);
+        let mut args = Vec::new();
+        loop {
+            let start = self.pos;
+            let arg = self.parse_nested_expression(true);
+            match self.peek() {
+                Some(Token::Semicolon(_)) if args.is_empty() && bound.is_none() => {
+                    let pos = token_pos(&self.tokens[start]);
+                    self.error(ErrorCode::InvalidCall, pos, self.end_of_previous() - pos, "bound must be a number");
+                    bound = Some(0);
+                }
+                Some(Token::Semicolon(pos)) => self.error(ErrorCode::UnexpectedToken, *pos, 1, "operator expected"),
+                Some(Token::Comma(_)) => args.push(arg),
+                _ => {
+                    args.push(arg);
+                    break;
+                }
+            }
+            self.pos += 1;
+        }
+        self.expect_close_paranthesis();
+
+        // Errors in the arguments would only cause follow-up errors here
+        if self.errors.len() > known_errors {
+            return placeholder();
+        }
+        match self.resolve_call(name, bound, args) {
+            Ok(expr) => expr,
+            Err(err) => {
+                self.errors.push(err.into());
+                placeholder()
+            }
+        }
+    }
+
+    /// Parses the bound `k;` in front of the arguments of a call, if any.
+    fn parse_bound(&mut self, open: &Token) -> Option<usize> {
+        let bound = match (self.peek(), self.tokens.get(self.pos + 1)) {
+            (Some(Token::Semicolon(_)), _) => {
+                self.error(ErrorCode::InvalidCall, token_pos(open) + 1, 0, "bound expected");
+                self.pos += 1;
+                return Some(0);
+            }
+            (Some(Token::Value(_, value)), Some(Token::Semicolon(_))) => if *value { 1 } else { 0 },
+            (Some(Token::Number(pos, value)), Some(Token::Semicolon(_))) => match value.parse() {
+                Ok(bound) => bound,
+                Err(_) => {
+                    self.error(ErrorCode::InvalidCall, *pos, value.len(), "bound too large");
+                    0
+                }
+            },
+            _ => return None
+        };
+        self.pos += 2;
+        Some(bound)
+    }
+
+    fn resolve_call(&self, name: &Token, bound: Option<usize>, mut args: Vec<Box<dyn expression::Expression>>) -> Result<Box<dyn expression::Expression>, ParseError> {
+        if let Some(function) = self.functions.get(token_name(name)) {
+            check_no_bound(name, bound)?;
+            if args.len() != function.params.len() {
+                return Err(ParseError {
+                    code: ErrorCode::InvalidCall,
+                    pos: token_pos(name),
+                    len: token_len(name),
+                    message: format!("function '{}' expects {} arguments, but got {}", token_name(name), function.params.len(), args.len()),
+                });
+            }
+            let bindings: BTreeMap<String, Box<dyn expression::Expression>> = function.params.iter().cloned().zip(args).collect();
+            return Ok(function.body.substitute(&bindings));
+        }
+
+        let operator = match token_name(name) {
+            "ite" => {
+                check_no_bound(name, bound)?;
+                if args.len() != 3 {
+                    return Err(ParseError {
+                        code: ErrorCode::InvalidCall,
+                        pos: token_pos(name),
+                        len: token_len(name),
+                        message: format!("function 'ite' expects 3 arguments, but got {}", args.len()),
+                    });
+                }
+                let if_false = args.pop().unwrap();
+                let if_true = args.pop().unwrap();
+                let condition = args.pop().unwrap();
+                return Ok(Box::new(ConditionalExpression::new(condition, if_true, if_false)));
+            }
+            "maj" => CardinalityOperator::MAJ,
+            "parity" => CardinalityOperator::PARITY,
+            "atleast" => CardinalityOperator::ATLEAST,
+            "atmost" => CardinalityOperator::ATMOST,
+            "exactly" => CardinalityOperator::EXACTLY,
+            _ => return Err(ParseError {
+                code: ErrorCode::InvalidCall,
+                pos: token_pos(name),
+                len: token_len(name),
+                message: format!("unknown function '{}'", token_name(name)),
             })
+        };
+        let k = match operator {
+            CardinalityOperator::MAJ | CardinalityOperator::PARITY => {
+                check_no_bound(name, bound)?;
+                0
+            }
+            _ => match bound {
+                Some(k) => k,
+                None => return Err(ParseError {
+                    code: ErrorCode::InvalidCall,
+                    pos: token_pos(name),
+                    len: token_len(name),
+                    message: format!("function '{}' expects a bound, e.g. '{}(1; a, b)'", token_name(name), token_name(name)),
+                })
+            }
+        };
+        Ok(Box::new(CardinalityExpression::new(operator, k, args)))
+    }
+
+    /// Parses `exists x, y. body`; the body extends as far to the right as possible.
+    fn parse_quantified_expression(&mut self) -> Box<dyn expression::Expression> {
+        let tokens = self.tokens;
+        let start = self.pos;
+        let quantifier = match token_name(&tokens[self.pos]) {
+            "exists" => Quantifier::EXISTS,
+            _ => Quantifier::FORALL
+        };
+
+        let mut variables = vec![token_name(&tokens[self.pos + 1])];
+        self.pos += 2;
+        while let (Some(Token::Comma(_)), Some(Token::Variable(_, name) | Token::QuotedVariable(_, name))) = (tokens.get(self.pos), tokens.get(self.pos + 1)) {
+            variables.push(name);
+            self.pos += 2;
         }
+        match self.peek() {
+            Some(Token::Dot(_)) => self.pos += 1,
+            next => {
+                self.error(ErrorCode::UnexpectedToken, self.end_of_previous(), 0, "\".\" expected");
+                if !next.is_some_and(starts_operand) {
+                    return placeholder();
+                }
+            }
+        }
+
+        let mut expr = self.parse_expression();
+        for variable in variables.iter().rev() {
+            expr = with_range(Box::new(QuantifiedExpression::new(quantifier, variable, expr)), self.range_from(start));
+        }
+        expr
+    }
+}
+
+/// Replaces the erroneous parts of an expression to continue parsing.
+fn placeholder() -> Box<dyn expression::Expression> {
+    Box::new(expression::Value::new(false))
+}
+
+/// Combines the topmost pending operator with the two topmost operands; an equivalence continuing a
+/// chain compares the last operand of the chain, `(a = b) = c` becomes `(a = b) & (b = c)`.
+fn reduce(operators: &mut Vec<&Token>, operands: &mut Vec<Box<dyn expression::Expression>>, chain: &mut Option<Box<dyn expression::Expression>>) {
+    let right = operands.pop().unwrap();
+    let left = operands.pop().unwrap();
+    let join = |left: &dyn expression::Expression, right: &dyn expression::Expression| match (left.range(), right.range()) {
+        (Some(left), Some(right)) => Some(left.join(&right)),
+        _ => None
+    };
+    let range = join(left.as_ref(), right.as_ref());
+    let operator = token_name(operators.pop().unwrap());
+    if let Some(last) = chain.take_if(|_| operator == "=") {
+        let link_range = join(last.as_ref(), right.as_ref());
+        let link = with_range(Box::new(BinaryExpression::new(BinaryOperator::EQ, last, right)), link_range);
+        operands.push(with_range(Box::new(BinaryExpression::new(BinaryOperator::AND, left, link)), range));
+        return;
     }
+    let operator = match operator {
+        "|" => BinaryOperator::OR,
+        "&" => BinaryOperator::AND,
+        "^" => BinaryOperator::XOR,
+        "=" => BinaryOperator::EQ,
+        "=>" => BinaryOperator::IMP,
+        "!&" => BinaryOperator::NAND,
+        "!|" => BinaryOperator::NOR,
+        "!^" => BinaryOperator::XNOR,
+        "<=" => BinaryOperator::RIMP,
+        "!=>" => BinaryOperator::NIMP,
+        operator => panic!("unsupported operator '{}'", operator)
+    };
+    operands.push(with_range(Box::new(BinaryExpression::new(operator, left, right)), range));
 }
 
-fn parse_single_token_expression(token: &Token) -> Result<Box<dyn expression::Expression>, ParseError> {
+/// Checks whether the token may start an operand.
+fn starts_operand(token: &Token) -> bool {
     match token {
-        Token::Value(_, value) => Ok(Box::new(expression::Value::new(*value))),
-        Token::Variable(_, name) => Ok(Box::new(expression::Variable::new(name))),
-        Token::Operator(pos, name) => Err(ParseError { pos: *pos, len: name.len(), message: String::from("value or variable expected") }),
-        Token::OpenParanthesis(pos) => Err(ParseError { pos: *pos, len: 1, message: String::from("value or variable expected") }),
-        Token::CloseParanthesis(pos) => Err(ParseError { pos: *pos, len: 1, message: String::from("value or variable expected") })
+        Token::Value(_, _) | Token::Variable(_, _) | Token::QuotedVariable(_, _) | Token::OpenParanthesis(_) => true,
+        Token::Operator(_, _) => matches!(token_name(token), "!" | "exists" | "forall"),
+        _ => false
     }
 }
 
-fn find_top_level_operator(tokens: &[Token]) -> Option<usize> {
+fn find_top_level_semicolon(tokens: &[Token]) -> Option<usize> {
     let mut plevel = 0;
-    let mut result: Option<(usize, &Token)> = None;
-
-    for i in 0..tokens.len() {
-        let current = &tokens[i];
-        match current {
-            Token::Operator(_, _) => if plevel == 0 {
-                if has_higher_precedence(result, current) {
-                    result = Some((i, current));
-                }
-            },
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
             Token::OpenParanthesis(_) => plevel += 1,
             Token::CloseParanthesis(_) => plevel -= 1,
+            Token::Semicolon(_) if plevel == 0 => return Some(i),
             _ => {}
         }
     }
+    None
+}
 
-    result.map(|(pos, _token)| pos)
+fn check_no_bound(name: &Token, bound: Option<usize>) -> Result<(), ParseError> {
+    match bound {
+        Some(_) => Err(ParseError {
+            code: ErrorCode::InvalidCall,
+            pos: token_pos(name),
+            len: token_len(name),
+            message: format!("function '{}' does not take a bound", token_name(name)),
+        }),
+        None => Ok(())
+    }
 }
 
-fn has_higher_precedence(current: Option<(usize, &Token)>, new: &Token) -> bool {
-    match current {
-        Some((_, Token::Operator(_, cname))) => {
-            match new {
-                Token::Operator(_, nname) =>
-                    get_precedence(cname) > get_precedence(nname),
-                _ =>
-                    false
-            }
-        }
-        _ => true
+/// Checks whether a quantifier like `exists x` or `∀y` starts at the given position.
+fn is_quantifier(tokens: &[Token], pos: usize) -> bool {
+    match (&tokens[pos], tokens.get(pos + 1)) {
+        (Token::Variable(_, _) | Token::Operator(_, _), Some(Token::Variable(_, _) | Token::QuotedVariable(_, _))) =>
+            matches!(token_name(&tokens[pos]), "exists" | "forall"),
+        _ => false
     }
 }
 
+fn is_binary_operator(operator: &str) -> bool {
+    matches!(operator, "|" | "&" | "^" | "=" | "=>" | "!&" | "!|" | "!^" | "<=" | "!=>")
+}
+
 fn get_precedence(operator: &str) -> usize {
     match operator {
-        "=" | "=>" => 0,
-        "|" | "^" => 1,
-        "&" => 2,
-        "!" => 3,
+        "?" | ":" => 0,
+        "=" | "=>" | "<=" | "!=>" => 1,
+        "|" | "^" | "!|" | "!^" => 2,
+        "&" | "!&" => 3,
+        "!" => 4,
+        _ => panic!("unsupported operator '{}'", operator)
+    }
+}
+
+/// Operators group to the left except for implications, which read as `a => (b => c)`.
+fn get_associativity(operator: &str) -> Associativity {
+    match operator {
+        "|" | "&" | "^" | "=" | "!&" | "!|" | "!^" | "<=" | "!=>" => Associativity::Left,
+        "=>" => Associativity::Right,
         _ => panic!("unsupported operator '{}'", operator)
     }
 }
 
+/*
+ * Tests
+ */
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
-    use crate::parser::{find_top_level_operator, parse_operator_expression, parse_paranthesis_expression, parse_single_token_expression, parse};
-    use crate::tokens::tokenize;
+    use crate::expression::Expression;
+    use crate::parser::{parse, parse_all, parse_with_options, ParseOptions};
+    use crate::tokens::{ErrorCode, tokenize};
 
     #[test]
-    fn find_top_level_operator_returns_none_if_no_operator() {
+    fn parse_return_err_if_operator_missing() {
         let tokens = tokenize("a b").unwrap_or_else(|_| vec![]);
-        assert_eq!(find_top_level_operator(&tokens), None)
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), true);
+        assert_eq!(result.err().unwrap().message, "operator expected");
     }
 
     #[test]
-    fn find_top_level_operator_returns_none_if_operators_in_paranthesis() {
-        let tokens = tokenize("(a &  b)").unwrap_or_else(|_| vec![]);
-        assert_eq!(find_top_level_operator(&tokens), None)
+    fn parse_groups_operators_to_the_left() {
+        let tokens = tokenize("a & b & c").unwrap_or_else(|_| vec![]);
+        assert_eq!(parse(&tokens).unwrap().to_dump_string(), "And(And(Variable(a),Variable(b)),Variable(c))");
+
+        let tokens = tokenize("a | b ^ c !^ d").unwrap_or_else(|_| vec![]);
+        assert_eq!(parse(&tokens).unwrap().to_dump_string(), "Xnor(Xor(Or(Variable(a),Variable(b)),Variable(c)),Variable(d))");
     }
 
     #[test]
-    fn find_top_level_operator_returns_first_matching_operator() {
-        let tokens = tokenize("a & b & c").unwrap_or_else(|_| vec![]);
-        assert_eq!(find_top_level_operator(&tokens), Some(1))
+    fn parse_groups_implications_to_the_right() {
+        let tokens = tokenize("a => b => c").unwrap_or_else(|_| vec![]);
+        assert_eq!(parse(&tokens).unwrap().to_dump_string(), "Imp(Variable(a),Imp(Variable(b),Variable(c)))");
+    }
+
+    #[test]
+    fn parse_groups_other_operators_to_the_left() {
+        let tokens = tokenize("a !& b !& c").unwrap_or_else(|_| vec![]);
+        assert_eq!(parse(&tokens).unwrap().to_dump_string(), "Nand(Nand(Variable(a),Variable(b)),Variable(c))");
     }
 
     #[test]
-    fn find_top_level_operator_returns_operator_with_highest_precedence() {
+    fn parse_respects_precedence() {
         let tokens = tokenize("!a & b ^ c | d = e => f").unwrap_or_else(|_| vec![]);
-        assert_eq!(find_top_level_operator(&tokens), Some(8));
+        assert_eq!(parse(&tokens).unwrap().to_dump_string(),
+                   "Eq(Or(Xor(And(Neg(Variable(a)),Variable(b)),Variable(c)),Variable(d)),Imp(Variable(e),Variable(f)))");
 
-        let tokens = tokenize("!a & b ^ c | e => f").unwrap_or_else(|_| vec![]);
-        assert_eq!(find_top_level_operator(&tokens), Some(8));
+        let tokens = tokenize("a | b & !c").unwrap_or_else(|_| vec![]);
+        assert_eq!(parse(&tokens).unwrap().to_dump_string(), "Or(Variable(a),And(Variable(b),Neg(Variable(c))))");
 
-        let tokens = tokenize("!a & b ^ c | e").unwrap_or_else(|_| vec![]);
-        assert_eq!(find_top_level_operator(&tokens), Some(4));
+        let tokens = tokenize("(a | b) & c").unwrap_or_else(|_| vec![]);
+        assert_eq!(parse(&tokens).unwrap().to_dump_string(), "And(Or(Variable(a),Variable(b)),Variable(c))");
+    }
 
-        let tokens = tokenize("!a & b | e").unwrap_or_else(|_| vec![]);
-        assert_eq!(find_top_level_operator(&tokens), Some(4));
+    #[test]
+    fn parse_with_options_overrides_associativity() {
+        let mut options = ParseOptions::default();
+        assert_eq!(options.set_associativity("=>:left").is_ok(), true);
+        assert_eq!(options.set_associativity("∧:right").is_ok(), true);
+        let tokens = tokenize("a => b => c & d & e").unwrap_or_else(|_| vec![]);
+        let result = parse_with_options(&tokens, &options, &mut Vec::new());
+        assert_eq!(result.unwrap().to_dump_string(),
+                   "Imp(Imp(Variable(a),Variable(b)),And(Variable(c),And(Variable(d),Variable(e))))");
+
+        assert_eq!(options.set_associativity("=>").err().unwrap(), "invalid associativity '=>', expected e.g. '=>:left'");
+        assert_eq!(options.set_associativity("!:left").err().unwrap(), "'!' is not a binary operator");
+    }
+
+    #[test]
+    fn parse_with_options_reads_chained_equivalences() {
+        let options = ParseOptions { chained_equivalence: true, ..ParseOptions::default() };
+        let tokens = tokenize("a = b = c = d").unwrap_or_else(|_| vec![]);
+        let result = parse_with_options(&tokens, &options, &mut Vec::new());
+        assert_eq!(result.unwrap().to_dump_string(),
+                   "And(And(Eq(Variable(a),Variable(b)),Eq(Variable(b),Variable(c))),Eq(Variable(c),Variable(d)))");
+
+        let tokens = tokenize("a = b & c = d | e = (f = g)").unwrap_or_else(|_| vec![]);
+        let result = parse_with_options(&tokens, &options, &mut Vec::new());
+        assert_eq!(result.unwrap().to_dump_string(),
+                   "And(And(Eq(Variable(a),And(Variable(b),Variable(c))),Eq(And(Variable(b),Variable(c)),Or(Variable(d),Variable(e)))),\
+                    Eq(Or(Variable(d),Variable(e)),Eq(Variable(f),Variable(g))))");
+    }
 
-        let tokens = tokenize("!a & b").unwrap_or_else(|_| vec![]);
-        assert_eq!(find_top_level_operator(&tokens), Some(2));
+    #[test]
+    fn parse_with_options_warns_about_ambiguous_chains() {
+        let tokens = tokenize("a => b => c").unwrap_or_else(|_| vec![]);
+        let mut warnings = Vec::new();
+        let result = parse_with_options(&tokens, &ParseOptions::default(), &mut warnings);
+        assert_eq!(result.is_err(), false);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].error.code, ErrorCode::AmbiguousChain);
+        assert_eq!((warnings[0].error.pos, warnings[0].error.len), (7, 2));
+        assert_eq!(warnings[0].error.message, "ambiguous chain of `=>` and `=>`");
+        assert_eq!((warnings[0].labels[0].pos, warnings[0].labels[0].len), (2, 2));
+        assert_eq!(warnings[0].help, Some(String::from("this is read as `a => (b => c)`, add parentheses to make this explicit")));
+
+        let tokens = tokenize("a | b ^ c !& d & e = (f = g)").unwrap_or_else(|_| vec![]);
+        let mut warnings = Vec::new();
+        parse_with_options(&tokens, &ParseOptions::default(), &mut warnings).unwrap();
+        let messages: Vec<&str> = warnings.iter().map(|warning| warning.error.message.as_str()).collect();
+        assert_eq!(messages, vec!["ambiguous chain of `|` and `^`", "ambiguous chain of `!&` and `&`"]);
+
+        let tokens = tokenize("a & b & c | d | (e = f) = g").unwrap_or_else(|_| vec![]);
+        let mut warnings = Vec::new();
+        parse_with_options(&tokens, &ParseOptions::default(), &mut warnings).unwrap();
+        assert_eq!(warnings.len(), 0);
 
-        let tokens = tokenize("!a").unwrap_or_else(|_| vec![]);
-        assert_eq!(find_top_level_operator(&tokens), Some(0));
+        let options = ParseOptions { chained_equivalence: true, ..ParseOptions::default() };
+        let tokens = tokenize("a = b = c").unwrap_or_else(|_| vec![]);
+        let mut warnings = Vec::new();
+        parse_with_options(&tokens, &options, &mut warnings).unwrap();
+        assert_eq!(warnings.len(), 0);
+    }
+
+    #[test]
+    fn parse_records_source_ranges() {
+        let tokens = tokenize("a & !(b | c) ? maj(a, b, c) : exists x. x").unwrap_or_else(|_| vec![]);
+        let expr = parse(&tokens).unwrap();
+        let range = |expr: &dyn Expression| expr.range().map(|range| (range.pos, range.len));
+        assert_eq!(range(expr.as_ref()), Some((0, 41)));
+        let condition = expr.children()[0];
+        assert_eq!(range(condition), Some((0, 12)));
+        assert_eq!(range(condition.children()[0]), Some((0, 1)));
+        assert_eq!(range(condition.children()[1]), Some((4, 8)));
+        assert_eq!(range(condition.children()[1].children()[0]), Some((5, 7)));
+        assert_eq!(range(condition.children()[1].children()[0].children()[1]), Some((10, 1)));
+        assert_eq!(range(expr.children()[1]), Some((15, 12)));
+        assert_eq!(range(expr.children()[1].children()[2]), Some((25, 1)));
+        assert_eq!(range(expr.children()[2]), Some((30, 11)));
+        assert_eq!(range(expr.children()[2].children()[0]), Some((40, 1)));
     }
 
     #[test]
-    fn parse_single_token_expression_return_ok_for_value_or_variable_token() {
+    fn parse_handles_long_chains() {
+        let str = vec!["a"; 5000].join(" | ");
+        let tokens = tokenize(&str).unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), false);
+
+        let str = vec!["a"; 5000].join(" & b | ");
+        let tokens = tokenize(&str).unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), false);
+    }
+
+    #[test]
+    fn parse_return_ok_for_value_or_variable_token() {
         let tokens = tokenize("0 1 a bc").unwrap_or_else(|_| vec![]);
 
-        let result = parse_single_token_expression(tokens.get(0).unwrap());
+        let result = parse(&tokens[..1]);
         assert_eq!(result.is_err(), false);
         assert_eq!(result.unwrap().to_dump_string(), "Value(0)");
 
-        let result = parse_single_token_expression(tokens.get(1).unwrap());
+        let result = parse(&tokens[1..=1]);
         assert_eq!(result.is_err(), false);
         assert_eq!(result.unwrap().to_dump_string(), "Value(1)");
 
-        let result = parse_single_token_expression(tokens.get(2).unwrap());
+        let result = parse(&tokens[2..=2]);
         assert_eq!(result.is_err(), false);
         assert_eq!(result.unwrap().to_dump_string(), "Variable(a)");
 
-        let result = parse_single_token_expression(tokens.get(3).unwrap());
+        let result = parse(&tokens[3..=3]);
         assert_eq!(result.is_err(), false);
         assert_eq!(result.unwrap().to_dump_string(), "Variable(bc)");
     }
 
     #[test]
-    fn parse_single_token_expression_return_err_for_operators() {
-        let tokens = tokenize("& ! ( )").unwrap_or_else(|_| vec![]);
+    fn parse_return_ok_for_quoted_variable_token() {
+        let tokens = tokenize("\"bus[3]\"").unwrap_or_else(|_| vec![]);
+
+        let result = parse(&tokens[..1]);
+        assert_eq!(result.is_err(), false);
+        assert_eq!(result.unwrap().to_dump_string(), "Variable(bus[3])");
+    }
+
+    #[test]
+    fn parse_return_err_for_single_operators() {
+        let tokens = tokenize("& : ( )").unwrap_or_else(|_| vec![]);
 
-        let result = parse_single_token_expression(tokens.get(0).unwrap());
+        let result = parse(&tokens[..1]);
         assert_eq!(result.is_err(), true);
         assert_eq!(result.err().unwrap().message, "value or variable expected");
 
-        let result = parse_single_token_expression(tokens.get(1).unwrap());
+        let result = parse(&tokens[1..=1]);
         assert_eq!(result.is_err(), true);
         assert_eq!(result.err().unwrap().message, "value or variable expected");
 
-        let result = parse_single_token_expression(tokens.get(2).unwrap());
+        let result = parse(&tokens[2..=2]);
         assert_eq!(result.is_err(), true);
         assert_eq!(result.err().unwrap().message, "value or variable expected");
 
-        let result = parse_single_token_expression(tokens.get(3).unwrap());
+        let result = parse(&tokens[3..=3]);
         assert_eq!(result.is_err(), true);
         assert_eq!(result.err().unwrap().message, "value or variable expected");
     }
 
     #[test]
-    fn parse_operator_expression_return_err_if_rhs_not_found() {
+    fn parse_return_err_if_rhs_not_found() {
         let tokens = tokenize("!").unwrap_or_else(|_| vec![]);
-        let result = parse_operator_expression(&tokens, 0);
+        let result = parse(&tokens);
         assert_eq!(result.is_err(), true);
         assert_eq!(result.err().unwrap().message, "missing right hand side operand");
 
         let tokens = tokenize("A&").unwrap_or_else(|_| vec![]);
-        let result = parse_operator_expression(&tokens, 1);
+        let result = parse(&tokens);
         assert_eq!(result.is_err(), true);
         assert_eq!(result.err().unwrap().message, "missing right hand side operand");
     }
 
     #[test]
-    fn parse_operator_expression_return_err_if_rhs_invalid() {
-        let tokens = tokenize("!|").unwrap_or_else(|_| vec![]);
-        let result = parse_operator_expression(&tokens, 0);
+    fn parse_return_err_if_rhs_invalid() {
+        let tokens = tokenize("! |").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
         assert_eq!(result.is_err(), true);
         assert_eq!(result.err().unwrap().message, "value or variable expected");
 
         let tokens = tokenize("A&|").unwrap_or_else(|_| vec![]);
-        let result = parse_operator_expression(&tokens, 1);
+        let result = parse(&tokens);
         assert_eq!(result.is_err(), true);
         assert_eq!(result.err().unwrap().message, "value or variable expected");
     }
 
     #[test]
-    fn parse_operator_expression_return_err_if_lhs_not_found() {
+    fn parse_return_err_if_lhs_not_found() {
         let tokens = tokenize("&a").unwrap_or_else(|_| vec![]);
-        let result = parse_operator_expression(&tokens, 0);
+        let result = parse(&tokens);
         assert_eq!(result.is_err(), true);
         assert_eq!(result.err().unwrap().message, "missing left hand side operand");
     }
 
     #[test]
-    fn parse_operator_expression_return_err_if_lhs_not_expected() {
+    fn parse_return_err_if_lhs_not_expected() {
         let tokens = tokenize("a!b").unwrap_or_else(|_| vec![]);
-        let result = parse_operator_expression(&tokens, 1);
+        let result = parse(&tokens);
         assert_eq!(result.is_err(), true);
         assert_eq!(result.err().unwrap().message, "unexpected left hand side operand");
     }
 
     #[test]
-    fn parse_operator_expression_return_ok_for_correct_expressions() {
+    fn parse_return_ok_for_correct_expressions() {
         let tokens = tokenize("!a a|b a&b a^b a=>b a=b").unwrap_or_else(|_| vec![]);
 
-        let result = parse_operator_expression(&tokens[..2], 0);
+        let result = parse(&tokens[..2]);
         assert_eq!(result.is_err(), false);
         assert_eq!(result.unwrap().to_dump_string(), "Neg(Variable(a))");
 
-        let result = parse_operator_expression(&tokens[2..5], 1);
+        let result = parse(&tokens[2..5]);
         assert_eq!(result.is_err(), false);
         assert_eq!(result.unwrap().to_dump_string(), "Or(Variable(a),Variable(b))");
 
-        let result = parse_operator_expression(&tokens[5..8], 1);
+        let result = parse(&tokens[5..8]);
         assert_eq!(result.is_err(), false);
         assert_eq!(result.unwrap().to_dump_string(), "And(Variable(a),Variable(b))");
 
-        let result = parse_operator_expression(&tokens[8..11], 1);
+        let result = parse(&tokens[8..11]);
         assert_eq!(result.is_err(), false);
         assert_eq!(result.unwrap().to_dump_string(), "Xor(Variable(a),Variable(b))");
 
-        let result = parse_operator_expression(&tokens[11..14], 1);
+        let result = parse(&tokens[11..14]);
         assert_eq!(result.is_err(), false);
         assert_eq!(result.unwrap().to_dump_string(), "Imp(Variable(a),Variable(b))");
 
-        let result = parse_operator_expression(&tokens[14..], 1);
+        let result = parse(&tokens[14..]);
         assert_eq!(result.is_err(), false);
         assert_eq!(result.unwrap().to_dump_string(), "Eq(Variable(a),Variable(b))");
     }
 
     #[test]
-    fn parse_paranthesis_expression_return_err_if_not_starting_with_paranthesis_open() {
-        let tokens = tokenize("a&b").unwrap_or_else(|_| vec![]);
-        let result = parse_paranthesis_expression(&tokens);
+    fn parse_return_ok_for_negated_and_reverse_operators() {
+        let tokens = tokenize("a!&b a!|b a!^b a<=b a!=>b").unwrap_or_else(|_| vec![]);
+
+        let result = parse(&tokens[..3]);
+        assert_eq!(result.is_err(), false);
+        assert_eq!(result.unwrap().to_dump_string(), "Nand(Variable(a),Variable(b))");
+
+        let result = parse(&tokens[3..6]);
+        assert_eq!(result.is_err(), false);
+        assert_eq!(result.unwrap().to_dump_string(), "Nor(Variable(a),Variable(b))");
+
+        let result = parse(&tokens[6..9]);
+        assert_eq!(result.is_err(), false);
+        assert_eq!(result.unwrap().to_dump_string(), "Xnor(Variable(a),Variable(b))");
+
+        let result = parse(&tokens[9..12]);
+        assert_eq!(result.is_err(), false);
+        assert_eq!(result.unwrap().to_dump_string(), "Rimp(Variable(a),Variable(b))");
+
+        let result = parse(&tokens[12..]);
+        assert_eq!(result.is_err(), false);
+        assert_eq!(result.unwrap().to_dump_string(), "Nimp(Variable(a),Variable(b))");
+    }
+
+    #[test]
+    fn parse_return_err_if_paranthesis_followed_by_operand() {
+        let tokens = tokenize("(a) b").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
         assert_eq!(result.is_err(), true);
         assert_eq!(result.err().unwrap().message, "operator expected");
     }
 
     #[test]
-    fn parse_paranthesis_expression_return_err_if_missing_paranthesis_close() {
+    fn parse_return_err_if_missing_paranthesis_close() {
         let tokens = tokenize("(a").unwrap_or_else(|_| vec![]);
-        let result = parse_paranthesis_expression(&tokens);
+        let result = parse(&tokens);
         assert_eq!(result.is_err(), true);
         assert_eq!(result.err().unwrap().message, "\")\" expected");
     }
 
     #[test]
-    fn parse_paranthesis_expression_return_err_if_unblanced_paranthesis() {
+    fn parse_return_err_if_unblanced_paranthesis() {
         let tokens = tokenize("(a))").unwrap_or_else(|_| vec![]);
-        let result = parse_paranthesis_expression(&tokens);
+        let result = parse(&tokens);
         assert_eq!(result.is_err(), true);
         assert_eq!(result.err().unwrap().message, "operator expected");
     }
 
     #[test]
-    fn parse_paranthesis_expression_return_err_if_more_than_one_paranthesis() {
+    fn parse_return_err_if_more_than_one_paranthesis() {
         let tokens = tokenize("(a)(b)").unwrap_or_else(|_| vec![]);
-        let result = parse_paranthesis_expression(&tokens);
+        let result = parse(&tokens);
         assert_eq!(result.is_err(), true);
         assert_eq!(result.err().unwrap().message, "operator expected");
     }
 
     #[test]
-    fn parse_paranthesis_expression_return_ok_for_complex_expressions_with_paranthesis() {
+    fn parse_return_ok_for_complex_expressions_with_paranthesis() {
         let tokens = tokenize("((a|b)&c)").unwrap_or_else(|_| vec![]);
-        let result = parse_paranthesis_expression(&tokens);
+        let result = parse(&tokens);
         assert_eq!(result.is_err(), false);
         assert_eq!(result.unwrap().to_dump_string(), "And(Or(Variable(a),Variable(b)),Variable(c))");
     }
 
+    #[test]
+    fn parse_respects_precedence_of_negated_and_reverse_operators() {
+        let tokens = tokenize("a !& b !| c <= d").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), false);
+        assert_eq!(result.unwrap().to_dump_string(), "Rimp(Nor(Nand(Variable(a),Variable(b)),Variable(c)),Variable(d))");
+    }
+
+    #[test]
+    fn parse_return_ok_for_conditional_expressions() {
+        let tokens = tokenize("s ? a : b").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), false);
+        assert_eq!(result.unwrap().to_dump_string(), "Ite(Variable(s),Variable(a),Variable(b))");
+    }
+
+    #[test]
+    fn parse_return_err_for_incomplete_expressions() {
+        let tokens = tokenize("? a : b").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), true);
+        assert_eq!(result.err().unwrap().message, "missing condition");
+
+        let tokens = tokenize("s ? a").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), true);
+        assert_eq!(result.err().unwrap().message, "\":\" expected");
+
+        let tokens = tokenize("s ? : b").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), true);
+        assert_eq!(result.err().unwrap().message, "missing then operand");
+
+        let tokens = tokenize("s ? a :").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), true);
+        assert_eq!(result.err().unwrap().message, "missing else operand");
+    }
+
+    #[test]
+    fn parse_parses_nested_conditionals() {
+        let tokens = tokenize("a = b ? c ? d : e : f ? g : h").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), false);
+        assert_eq!(result.unwrap().to_dump_string(),
+                   "Ite(Eq(Variable(a),Variable(b)),Ite(Variable(c),Variable(d),Variable(e)),Ite(Variable(f),Variable(g),Variable(h)))");
+
+        let tokens = tokenize("a : b").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), true);
+        assert_eq!(result.err().unwrap().message, "\":\" without matching \"?\"");
+    }
+
+    #[test]
+    fn parse_return_ok_for_ite() {
+        let tokens = tokenize("ite(s, a | b, (c))").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), false);
+        assert_eq!(result.unwrap().to_dump_string(), "Ite(Variable(s),Or(Variable(a),Variable(b)),Variable(c))");
+    }
+
+    #[test]
+    fn parse_return_err_for_invalid_calls() {
+        let tokens = tokenize("ite(s, a)").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), true);
+        assert_eq!(result.err().unwrap().message, "function 'ite' expects 3 arguments, but got 2");
+
+        let tokens = tokenize("ite(s, , b)").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), true);
+        assert_eq!(result.err().unwrap().message, "empty argument");
+
+        let tokens = tokenize("foo(s)").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), true);
+        assert_eq!(result.err().unwrap().message, "unknown function 'foo'");
+
+        let tokens = tokenize("ite(s, a, b").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), true);
+        assert_eq!(result.err().unwrap().message, "\")\" expected");
+    }
+
+    #[test]
+    fn parse_return_ok_for_cardinality_functions() {
+        let tokens = tokenize("maj(a, b, c)").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), false);
+        assert_eq!(result.unwrap().to_dump_string(), "Maj(Variable(a),Variable(b),Variable(c))");
+
+        let tokens = tokenize("parity(a, b & c)").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), false);
+        assert_eq!(result.unwrap().to_dump_string(), "Parity(Variable(a),And(Variable(b),Variable(c)))");
+
+        let tokens = tokenize("atmost(2; a, b, c, d)").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), false);
+        assert_eq!(result.unwrap().to_dump_string(), "Atmost(2;Variable(a),Variable(b),Variable(c),Variable(d))");
+
+        let tokens = tokenize("exactly(1; a, atleast(0; b))").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), false);
+        assert_eq!(result.unwrap().to_dump_string(), "Exactly(1;Variable(a),Atleast(0;Variable(b)))");
+
+        let tokens = tokenize("maj(atmost(1; a), b, c)").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), false);
+        assert_eq!(result.unwrap().to_dump_string(), "Maj(Atmost(1;Variable(a)),Variable(b),Variable(c))");
+    }
+
+    #[test]
+    fn parse_return_err_for_invalid_bounds() {
+        let tokens = tokenize("atmost(a, b)").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), true);
+        assert_eq!(result.err().unwrap().message, "function 'atmost' expects a bound, e.g. 'atmost(1; a, b)'");
+
+        let tokens = tokenize("maj(2; a, b)").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), true);
+        assert_eq!(result.err().unwrap().message, "function 'maj' does not take a bound");
+
+        let tokens = tokenize("exactly(a; a, b)").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), true);
+        assert_eq!(result.err().unwrap().message, "bound must be a number");
+
+        let tokens = tokenize("exactly(; a, b)").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), true);
+        assert_eq!(result.err().unwrap().message, "bound expected");
+    }
+
+    #[test]
+    fn parse_expands_user_defined_functions() {
+        let tokens = tokenize("def full_add(a, b, c) = a ^ b ^ c; def carry(a, b, c) = maj(a, b, c); full_add(x, y & a, carry(a, b, z))").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), false);
+        assert_eq!(result.unwrap().to_dump_string(),
+                   "Xor(Xor(Variable(x),And(Variable(y),Variable(a))),Maj(Variable(a),Variable(b),Variable(z)))");
+    }
+
+    #[test]
+    fn parse_allows_def_as_variable_name() {
+        let tokens = tokenize("def & a").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), false);
+        assert_eq!(result.unwrap().to_dump_string(), "And(Variable(def),Variable(a))");
+    }
+
+    #[test]
+    fn parse_return_err_for_invalid_function_calls() {
+        let tokens = tokenize("def f(a, b) = a & b; f(x)").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), true);
+        let err = result.err().unwrap();
+        assert_eq!(err.message, "function 'f' expects 2 arguments, but got 1");
+        assert_eq!(err.pos, 21);
+
+        let tokens = tokenize("def f(a) = g(a); def g(a) = a; f(x)").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), true);
+        assert_eq!(result.err().unwrap().message, "unknown function 'g'");
+    }
+
+    #[test]
+    fn parse_all_reports_errors_of_all_operands() {
+        let check = |input: &str, expected: Vec<(usize, &str)>| {
+            let tokens = tokenize(input).unwrap_or_else(|_| vec![]);
+            let errors = parse_all(&tokens).err().unwrap();
+            let mut messages: Vec<(usize, &str)> = errors.iter().map(|err| (err.error.pos, err.error.message.as_str())).collect();
+            messages.sort();
+            assert_eq!(messages, expected, "{}", input);
+        };
+
+        check("(a b) & !(c d)", vec![(3, "operator expected"), (12, "operator expected")]);
+        check("x ? (a b) : f(c d, &)", vec![(7, "operator expected"), (16, "operator expected"), (19, "value or variable expected")]);
+        check("exists x. (a b) | (c", vec![(13, "operator expected"), (20, "\")\" expected")]);
+        check("a; def f(a, a) = a; f(x) & (y z)", vec![(1, "\";\" is only allowed after function definitions"),
+                                                   (12, "parameter 'a' declared twice"),
+                                                   (30, "operator expected")]);
+    }
+
+    #[test]
+    fn parse_all_returns_the_error_of_parse_first() {
+        let tokens = tokenize("(a b) & c ? d").unwrap_or_else(|_| vec![]);
+        let errors = parse_all(&tokens).err().unwrap();
+        assert_eq!(errors[0].error.message, parse(&tokens).err().unwrap().message);
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn parse_all_adds_hints() {
+        let tokens = tokenize("(a & (b | c)").unwrap_or_else(|_| vec![]);
+        let errors = parse_all(&tokens).err().unwrap();
+        assert_eq!(errors[0].labels.len(), 1);
+        assert_eq!(errors[0].labels[0].message, "unbalanced `(` opened here");
+        assert_eq!(errors[0].labels[0].pos, 0);
+
+        let tokens = tokenize("a) | b").unwrap_or_else(|_| vec![]);
+        let errors = parse_all(&tokens).err().unwrap();
+        assert_eq!(errors[0].labels[0].message, "unbalanced `)`");
+        assert_eq!(errors[0].labels[0].pos, 1);
+
+        let tokens = tokenize("a or b").unwrap_or_else(|_| vec![]);
+        let errors = parse_all(&tokens).err().unwrap();
+        assert_eq!(errors[0].help.as_deref(), Some("did you mean `|`?"));
+    }
+
+    #[test]
+    fn parse_classifies_errors_by_code() {
+        let check = |input: &str, code: ErrorCode| {
+            let tokens = tokenize(input).unwrap_or_else(|_| vec![]);
+            assert_eq!(parse(&tokens).err().unwrap().code, code);
+        };
+
+        check("a b", ErrorCode::UnexpectedToken);
+        check("a &", ErrorCode::MissingOperand);
+        check("foo(a)", ErrorCode::InvalidCall);
+        check("atmost(x; a)", ErrorCode::InvalidCall);
+        check("def f(a, a) = a; f(x)", ErrorCode::InvalidDefinition);
+        check("def f(a) = a;", ErrorCode::MissingInput);
+    }
+
+    #[test]
+    fn parse_return_err_for_invalid_function_definitions() {
+        let check = |input: &str, message: &str| {
+            let tokens = tokenize(input).unwrap_or_else(|_| vec![]);
+            let result = parse(&tokens);
+            assert_eq!(result.is_err(), true);
+            assert_eq!(result.err().unwrap().message, message);
+        };
+
+        check("def f(a) = a", "\";\" expected after function definition");
+        check("def f(a) = a;", "expression expected after function definitions");
+        check("a; b", "\";\" is only allowed after function definitions");
+        check("def f a = a; f(x)", "\"(\" expected");
+        check("def f(a b) = a; f(x)", "\",\" or \")\" expected");
+        check("def f(a, a) = a; f(x)", "parameter 'a' declared twice");
+        check("def f(a, 1) = a; f(x)", "parameter name expected");
+        check("def f(a) a; f(x)", "\"=\" expected");
+        check("def f(a) =; f(x)", "missing function body");
+        check("def maj(a) = a; f(x)", "function 'maj' is already defined");
+        check("def f(a) = a; def f(a) = a; f(x)", "function 'f' is already defined");
+    }
+
+    #[test]
+    fn parse_parses_quantifiers() {
+        let tokens = tokenize("a & exists x, y. x | y => a").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), false);
+        assert_eq!(result.unwrap().to_dump_string(),
+                   "And(Variable(a),Exists(x,Exists(y,Imp(Or(Variable(x),Variable(y)),Variable(a)))))");
+
+        let tokens = tokenize("∀x. ∃y. !(x = y)").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), false);
+        assert_eq!(result.unwrap().to_dump_string(), "Forall(x,Exists(y,Neg(Eq(Variable(x),Variable(y)))))");
+
+        let tokens = tokenize("exists x.x | y").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), false);
+        assert_eq!(result.unwrap().to_dump_string(), "Exists(x,Or(Variable(x),Variable(y)))");
+
+        let tokens = tokenize("∀x.x").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), false);
+        assert_eq!(result.unwrap().to_dump_string(), "Forall(x,Variable(x))");
+
+        let tokens = tokenize("exists a, b.c.d").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), false);
+        assert_eq!(result.unwrap().to_dump_string(), "Exists(a,Exists(b,Variable(c.d)))");
+
+        let tokens = tokenize("exists & forall").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), false);
+        assert_eq!(result.unwrap().to_dump_string(), "And(Variable(exists),Variable(forall))");
+    }
+
+    #[test]
+    fn parse_return_err_for_invalid_quantifiers() {
+        let tokens = tokenize("exists x y").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), true);
+        assert_eq!(result.err().unwrap().message, "\".\" expected");
+
+        let tokens = tokenize("forall x, y.").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), true);
+        assert_eq!(result.err().unwrap().message, "missing quantifier body");
+    }
+
+    #[test]
+    fn parse_avoids_capture_when_expanding_functions() {
+        let tokens = tokenize("def f(a) = exists x. a & x; f(x)").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), false);
+        assert_eq!(result.unwrap().to_string(), "exists x_1. x & x_1");
+    }
+
+    #[test]
+    fn parse_parses_unicode_operators() {
+        let tokens = tokenize("¬größe ∧ b → c").unwrap_or_else(|_| vec![]);
+        let result = parse(&tokens);
+        assert_eq!(result.is_err(), false);
+        assert_eq!(result.unwrap().to_dump_string(), "Imp(And(Neg(Variable(größe)),Variable(b)),Variable(c))");
+    }
+
     #[test]
     fn parse_parses_complex_expressions() {
         let tokens = tokenize("(a|b&c) = ((a|b)&c)").unwrap_or_else(|_| vec![]);
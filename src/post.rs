@@ -0,0 +1,122 @@
+use std::collections::BTreeSet;
+
+use crate::anf::{Anf, truth_table};
+use crate::diagnostic::display_width;
+use crate::expression::Expression;
+
+/*
+ * Classification
+ */
+
+/// The maximal classes of Post's lattice a Boolean function belongs to. By Post's theorem a set of
+/// functions is functionally complete iff it has a function outside of each of the classes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Classification {
+    /// `f(0, ..., 0) = 0`
+    pub preserves_zero: bool,
+    /// `f(1, ..., 1) = 1`
+    pub preserves_one: bool,
+    /// Changing an argument from `0` to `1` never changes the value from `1` to `0`
+    pub monotone: bool,
+    /// `f(!x1, ..., !xn) = !f(x1, ..., xn)`
+    pub self_dual: bool,
+    /// The algebraic normal form has no monomial with more than one variable
+    pub affine: bool,
+}
+
+const CLASSES: [&str; 5] = ["0-preserving", "1-preserving", "monotone", "self-dual", "affine"];
+
+impl Classification {
+    pub fn from_expression(expr: &dyn Expression) -> Result<Classification, String> {
+        let (variables, table) = truth_table(expr)?;
+        let all = table.len() - 1;
+        Ok(Classification {
+            preserves_zero: !table[0],
+            preserves_one: table[all],
+            monotone: (0..table.len()).all(|i| (0..variables.len()).all(|bit| !table[i] || table[i | (1 << bit)])),
+            self_dual: (0..table.len()).all(|i| table[i] != table[i ^ all]),
+            affine: Anf::with_polarity(expr, &BTreeSet::new())?.degree() <= 1,
+        })
+    }
+
+    fn classes(&self) -> [bool; 5] {
+        [self.preserves_zero, self.preserves_one, self.monotone, self.self_dual, self.affine]
+    }
+}
+
+/// Returns the classes of Post's lattice that contain all of the functions; the functions are
+/// functionally complete iff there is none.
+pub fn common_classes(functions: &[Classification]) -> Vec<&'static str> {
+    (0..CLASSES.len())
+        .filter(|class| functions.iter().all(|function| function.classes()[*class]))
+        .map(|class| CLASSES[class])
+        .collect()
+}
+
+/// Returns a table with a row for each function and a column for each class.
+pub fn format_classifications(functions: &[(String, Classification)]) -> String {
+    let width = functions.iter().map(|(name, _)| display_width(name)).chain([8]).max().unwrap();
+    let mut result = format!("| {}{} |", "function", " ".repeat(width - 8));
+    for class in CLASSES {
+        result.push_str(&format!(" {} |", class));
+    }
+    result.push_str(&format!("\n+{}+", "-".repeat(width + 2)));
+    for class in CLASSES {
+        result.push_str(&format!("{}+", "-".repeat(class.len() + 2)));
+    }
+    result.push('\n');
+    for (name, classification) in functions {
+        result.push_str(&format!("| {}{} |", name, " ".repeat(width - display_width(name))));
+        for (class, member) in CLASSES.iter().zip(classification.classes()) {
+            result.push_str(&format!(" {:width$} |", if member { "yes" } else { "no" }, width = class.len()));
+        }
+        result.push('\n');
+    }
+    result
+}
+
+/*
+ * Tests
+ */
+
+#[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+    use crate::tokens::tokenize;
+
+    fn classify(str: &str) -> Classification {
+        let expr = parse(&tokenize(str).unwrap_or_else(|_| vec![])).ok().unwrap();
+        Classification::from_expression(expr.as_ref()).ok().unwrap()
+    }
+
+    #[test]
+    fn from_expression_classifies_functions() {
+        assert_eq!(classify("a & b").classes(), [true, true, true, false, false]);
+        assert_eq!(classify("a ^ b").classes(), [true, false, false, false, true]);
+        assert_eq!(classify("!a").classes(), [false, false, false, true, true]);
+        assert_eq!(classify("maj(a, b, c)").classes(), [true, true, true, true, false]);
+        assert_eq!(classify("a => b").classes(), [false, true, false, false, false]);
+        assert_eq!(classify("0").classes(), [true, false, true, false, true]);
+        assert_eq!(classify("a").classes(), [true, true, true, true, true]);
+    }
+
+    #[test]
+    fn common_classes_decides_completeness() {
+        assert_eq!(common_classes(&[classify("a !& b")]), Vec::<&str>::new());
+        assert_eq!(common_classes(&[classify("a & b"), classify("!a")]), Vec::<&str>::new());
+        assert_eq!(common_classes(&[classify("a => b"), classify("0")]), Vec::<&str>::new());
+        assert_eq!(common_classes(&[classify("a & b"), classify("a | b")]), vec!["0-preserving", "1-preserving", "monotone"]);
+        assert_eq!(common_classes(&[classify("a ^ b"), classify("!a")]), vec!["affine"]);
+    }
+
+    #[test]
+    fn format_classifications_prints_a_table() {
+        let table = format_classifications(&[(String::from("a => b"), classify("a => b")), (String::from("0"), classify("0"))]);
+        assert_eq!(table, "| function | 0-preserving | 1-preserving | monotone | self-dual | affine |\n\
+            +----------+--------------+--------------+----------+-----------+--------+\n\
+            | a => b   | no           | yes          | no       | no        | no     |\n\
+            | 0        | yes          | no           | yes      | no        | yes    |\n");
+    }
+}
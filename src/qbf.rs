@@ -0,0 +1,450 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::cnf::Cnf;
+use crate::expression::{BinaryExpression, BinaryOperator, Expression, free_variables, QuantifiedExpression, Quantifier, UnaryExpression, UnaryOperator, Value, Variable};
+
+/*
+ * Qbf
+ */
+
+/// A quantified Boolean formula in prenex normal form with its matrix in CNF.
+pub struct Qbf {
+    /// The quantifier blocks, outermost first; free variables form an outermost existential block.
+    pub prefix: Vec<(Quantifier, Vec<usize>)>,
+    pub cnf: Cnf,
+}
+
+impl Qbf {
+    /// Converts the expression into prenex normal form and its matrix into CNF.
+    pub fn from_expression(expr: &dyn Expression) -> Result<Qbf, String> {
+        let free = free_variables(expr);
+        let mut used = free.clone();
+        let mut quantifiers = Vec::new();
+        let matrix = prenex(expr, false, &mut quantifiers, &mut used)?;
+
+        let mut names: Vec<String> = free.into_iter().collect();
+        let mut prefix: Vec<(Quantifier, Vec<usize>)> = Vec::new();
+        if !names.is_empty() {
+            prefix.push((Quantifier::EXISTS, (1..=names.len()).collect()));
+        }
+        for (quantifier, var) in quantifiers {
+            names.push(var);
+            match prefix.last_mut() {
+                Some((last, vars)) if *last == quantifier => vars.push(names.len()),
+                _ => prefix.push((quantifier, vec![names.len()]))
+            }
+        }
+
+        let mut cnf = Cnf::new(names);
+        cnf.add_expression(matrix.as_ref())?;
+        let named = cnf.names.len();
+        if cnf.variable_count > named {
+            let aux = ((named + 1)..=cnf.variable_count).collect();
+            match prefix.last_mut() {
+                Some((Quantifier::EXISTS, vars)) => vars.extend(aux),
+                _ => prefix.push((Quantifier::EXISTS, aux))
+            }
+        }
+        Ok(Qbf { prefix, cnf })
+    }
+
+    /// Decides the formula; if it is true, the returned map contains values for the variables of an
+    /// outermost existential block that make the rest of the formula true.
+    pub fn solve(&self) -> Option<BTreeMap<String, bool>> {
+        let mut solver = Solver::new(self);
+        if !solver.solve() {
+            return None;
+        }
+
+        let mut witness = BTreeMap::new();
+        if let Some((Quantifier::EXISTS, vars)) = self.prefix.first() {
+            for var in vars.iter().filter(|var| **var <= self.cnf.names.len()) {
+                solver.values[*var] = Some(true);
+                if !solver.solve() {
+                    solver.values[*var] = Some(false);
+                }
+                witness.insert(self.cnf.names[var - 1].clone(), solver.values[*var].unwrap());
+            }
+        }
+        Some(witness)
+    }
+
+    /// Returns the formula in QDIMACS format; the names of the variables are given as comments.
+    pub fn to_qdimacs(&self) -> String {
+        let mut result = String::new();
+        for (i, name) in self.cnf.names.iter().enumerate() {
+            result.push_str(&format!("c var {} {}\n", i + 1, name));
+        }
+        result.push_str(&format!("p cnf {} {}\n", self.cnf.variable_count, self.cnf.clauses.len()));
+        for (quantifier, vars) in &self.prefix {
+            result.push(if *quantifier == Quantifier::EXISTS { 'e' } else { 'a' });
+            for var in vars {
+                result.push_str(&format!(" {}", var));
+            }
+            result.push_str(" 0\n");
+        }
+        for clause in &self.cnf.clauses {
+            for literal in clause {
+                result.push_str(&format!("{} ", literal));
+            }
+            result.push_str("0\n");
+        }
+        result
+    }
+}
+
+/// Pulls all quantifiers of the expression to the front, renaming bound variables that are already
+/// used; returns the quantifier free matrix. `negated` tells whether the expression is in the scope
+/// of an odd number of negations, in this case the quantifiers are flipped.
+fn prenex(expr: &dyn Expression, negated: bool, prefix: &mut Vec<(Quantifier, String)>, used: &mut BTreeSet<String>) -> Result<Box<dyn Expression>, String> {
+    if let Some(quantified) = expr.as_quantified() {
+        let quantifier = match (quantified.quantifier, negated) {
+            (Quantifier::EXISTS, false) | (Quantifier::FORALL, true) => Quantifier::EXISTS,
+            _ => Quantifier::FORALL
+        };
+        if !used.contains(&quantified.var) {
+            used.insert(quantified.var.clone());
+            prefix.push((quantifier, quantified.var.clone()));
+            return prenex(quantified.body.as_ref(), negated, prefix, used);
+        }
+        let mut i = 1;
+        while used.contains(&format!("{}_{}", quantified.var, i)) {
+            i += 1;
+        }
+        let fresh = format!("{}_{}", quantified.var, i);
+        let mut bindings: BTreeMap<String, Box<dyn Expression>> = BTreeMap::new();
+        bindings.insert(quantified.var.clone(), Box::new(Variable::new(&fresh)));
+        used.insert(fresh.clone());
+        prefix.push((quantifier, fresh));
+        return prenex(quantified.body.substitute(&bindings).as_ref(), negated, prefix, used);
+    }
+    if let Some(unary) = expr.as_unary() {
+        return Ok(Box::new(UnaryExpression::new(unary.op, prenex(unary.arg.as_ref(), !negated, prefix, used)?)));
+    }
+    if let Some(binary) = expr.as_binary() {
+        let (left_negated, right_negated) = match binary.op {
+            BinaryOperator::AND | BinaryOperator::OR => (false, false),
+            BinaryOperator::NAND | BinaryOperator::NOR => (true, true),
+            BinaryOperator::IMP => (true, false),
+            BinaryOperator::RIMP | BinaryOperator::NIMP => (false, true),
+            _ => return check_quantifier_free(expr)
+        };
+        let left = prenex(binary.left.as_ref(), negated != left_negated, prefix, used)?;
+        let right = prenex(binary.right.as_ref(), negated != right_negated, prefix, used)?;
+        return Ok(Box::new(BinaryExpression::new(binary.op, left, right)));
+    }
+    check_quantifier_free(expr)
+}
+
+fn check_quantifier_free(expr: &dyn Expression) -> Result<Box<dyn Expression>, String> {
+    if contains_quantifier(expr) {
+        return Err(format!("quantifiers inside '{}' cannot be moved to the front, please rewrite it", expr.to_string()));
+    }
+    Ok(expr.clone_box())
+}
+
+fn contains_quantifier(expr: &dyn Expression) -> bool {
+    expr.as_quantified().is_some() || expr.children().into_iter().any(contains_quantifier)
+}
+
+/*
+ * QDIMACS import
+ */
+
+/// Reads a formula in QDIMACS format; variables are named as given by `c var <n> <name>` comments,
+/// otherwise `x<n>`. Variables not mentioned in the prefix are free.
+pub fn from_qdimacs(str: &str) -> Result<Box<dyn Expression>, String> {
+    let mut names: BTreeMap<i64, String> = BTreeMap::new();
+    let mut prefix: Vec<(Quantifier, i64)> = Vec::new();
+    let mut clauses: Vec<Vec<i64>> = Vec::new();
+    let mut clause = Vec::new();
+    let mut header = false;
+
+    for (i, line) in str.lines().enumerate() {
+        let line = line.trim();
+        let mut words = line.split_whitespace();
+        match words.next() {
+            None => {}
+            Some("c") => {
+                let parts: Vec<&str> = line.splitn(4, ' ').collect();
+                if parts.len() == 4 && parts[1] == "var" {
+                    if let Ok(var) = parts[2].parse() {
+                        names.insert(var, parts[3].to_string());
+                    }
+                }
+            }
+            Some("p") => {
+                if header || words.next() != Some("cnf") {
+                    return Err(format!("line {}: invalid problem line", i + 1));
+                }
+                header = true;
+            }
+            Some(word) if !header => return Err(format!("line {}: problem line expected, but got '{}'", i + 1, word)),
+            Some(word) if word == "a" || word == "e" => {
+                if !clauses.is_empty() || !clause.is_empty() {
+                    return Err(format!("line {}: quantifiers must precede the clauses", i + 1));
+                }
+                let quantifier = if word == "a" { Quantifier::FORALL } else { Quantifier::EXISTS };
+                for literal in parse_literals(i, words)?.into_iter().filter(|literal| *literal != 0) {
+                    prefix.push((quantifier, literal.abs()));
+                }
+            }
+            Some(_) => {
+                for literal in parse_literals(i, line.split_whitespace())? {
+                    if literal == 0 {
+                        clauses.push(clause);
+                        clause = Vec::new();
+                    } else {
+                        clause.push(literal);
+                    }
+                }
+            }
+        }
+    }
+    if !header {
+        return Err(String::from("missing problem line"));
+    }
+    if !clause.is_empty() {
+        clauses.push(clause);
+    }
+
+    let name = |var: i64| names.get(&var).cloned().unwrap_or_else(|| format!("x{}", var));
+    let mut matrix: Option<Box<dyn Expression>> = None;
+    for clause in clauses.into_iter().rev() {
+        let mut disjunction: Option<Box<dyn Expression>> = None;
+        for literal in clause.into_iter().rev() {
+            let mut expr: Box<dyn Expression> = Box::new(Variable::new(&name(literal.abs())));
+            if literal < 0 {
+                expr = Box::new(UnaryExpression::new(UnaryOperator::NEG, expr));
+            }
+            disjunction = Some(match disjunction {
+                Some(right) => Box::new(BinaryExpression::new(BinaryOperator::OR, expr, right)),
+                None => expr
+            });
+        }
+        let disjunction = disjunction.unwrap_or_else(|| Box::new(Value::new(false)));
+        matrix = Some(match matrix {
+            Some(right) => Box::new(BinaryExpression::new(BinaryOperator::AND, disjunction, right)),
+            None => disjunction
+        });
+    }
+
+    let mut expr = matrix.unwrap_or_else(|| Box::new(Value::new(true)));
+    for (quantifier, var) in prefix.into_iter().rev() {
+        expr = Box::new(QuantifiedExpression::new(quantifier, &name(var), expr));
+    }
+    Ok(expr)
+}
+
+fn parse_literals<'a>(line: usize, words: impl Iterator<Item=&'a str>) -> Result<Vec<i64>, String> {
+    words.map(|word| word.parse::<i64>().map_err(|_| format!("line {}: invalid literal '{}'", line + 1, word)))
+        .collect()
+}
+
+/*
+ * Solver
+ */
+
+/// A QDPLL solver: it branches on the variables in prefix order and applies unit propagation with
+/// universal reduction.
+struct Solver<'a> {
+    clauses: &'a [Vec<i64>],
+    order: Vec<usize>,
+    level: Vec<usize>,
+    universal: Vec<bool>,
+    values: Vec<Option<bool>>,
+}
+
+impl<'a> Solver<'a> {
+    fn new(qbf: &'a Qbf) -> Solver<'a> {
+        let count = qbf.cnf.variable_count + 1;
+        let mut solver = Solver {
+            clauses: &qbf.cnf.clauses,
+            order: Vec::new(),
+            level: vec![0; count],
+            universal: vec![false; count],
+            values: vec![None; count],
+        };
+        for (level, (quantifier, vars)) in qbf.prefix.iter().enumerate() {
+            for var in vars {
+                solver.order.push(*var);
+                solver.level[*var] = level;
+                solver.universal[*var] = *quantifier == Quantifier::FORALL;
+            }
+        }
+        solver
+    }
+
+    fn solve(&mut self) -> bool {
+        let mut trail = Vec::new();
+        let result = match self.propagate(&mut trail) {
+            Some(result) => result,
+            None => {
+                let var = *self.order.iter().find(|var| self.values[**var].is_none()).unwrap();
+                self.values[var] = Some(true);
+                let if_true = self.solve();
+                let result = if if_true != self.universal[var] {
+                    if_true
+                } else {
+                    self.values[var] = Some(false);
+                    self.solve()
+                };
+                self.values[var] = None;
+                result
+            }
+        };
+        for var in trail {
+            self.values[var] = None;
+        }
+        result
+    }
+
+    /// Assigns unit literals until nothing changes; returns the result if it is known already.
+    fn propagate(&mut self, trail: &mut Vec<usize>) -> Option<bool> {
+        loop {
+            let mut satisfied = true;
+            let mut unit = None;
+            for clause in self.clauses {
+                if clause.iter().any(|literal| self.values[literal.unsigned_abs() as usize] == Some(*literal > 0)) {
+                    continue;
+                }
+                satisfied = false;
+
+                let open: Vec<i64> = clause.iter().copied().filter(|literal| self.values[literal.unsigned_abs() as usize].is_none()).collect();
+                let innermost = open.iter()
+                    .map(|literal| literal.unsigned_abs() as usize)
+                    .filter(|var| !self.universal[*var])
+                    .map(|var| self.level[var])
+                    .max();
+                let reduced: Vec<i64> = open.into_iter()
+                    .filter(|literal| {
+                        let var = literal.unsigned_abs() as usize;
+                        !self.universal[var] || innermost.is_some_and(|level| self.level[var] < level)
+                    })
+                    .collect();
+                match reduced.len() {
+                    0 => return Some(false),
+                    1 if !self.universal[reduced[0].unsigned_abs() as usize] => unit = unit.or(Some(reduced[0])),
+                    _ => {}
+                }
+            }
+            if satisfied {
+                return Some(true);
+            }
+            match unit {
+                Some(literal) => {
+                    let var = literal.unsigned_abs() as usize;
+                    self.values[var] = Some(literal > 0);
+                    trail.push(var);
+                }
+                None => return None
+            }
+        }
+    }
+}
+
+
+/*
+ * Tests
+ */
+
+#[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
+mod tests {
+    use super::*;
+    use crate::expression::EvaluationContext;
+    use crate::parser::parse;
+    use crate::tokens::tokenize;
+
+    fn parse_str(str: &str) -> Box<dyn Expression> {
+        parse(&tokenize(str).unwrap_or_else(|_| vec![])).ok().unwrap()
+    }
+
+    fn solve(str: &str) -> Option<BTreeMap<String, bool>> {
+        Qbf::from_expression(parse_str(str).as_ref()).unwrap().solve()
+    }
+
+    #[test]
+    fn from_expression_moves_quantifiers_to_the_front() {
+        let qbf = Qbf::from_expression(parse_str("a & !(forall x. x | a) | exists x. x => forall y. y").as_ref()).unwrap();
+        assert_eq!(qbf.cnf.names, vec!["a", "x", "x_1", "y"]);
+        assert_eq!(qbf.prefix[0], (Quantifier::EXISTS, vec![1, 2, 3]));
+        assert_eq!(qbf.prefix[1].0, Quantifier::FORALL);
+        assert_eq!(qbf.prefix[1].1, vec![4]);
+    }
+
+    #[test]
+    fn from_expression_returns_err_for_quantifiers_below_equivalence() {
+        let result = Qbf::from_expression(parse_str("a = exists x. x").as_ref());
+        assert_eq!(result.is_err(), true);
+        assert_eq!(result.err().unwrap(), "quantifiers inside 'a = (exists x. x)' cannot be moved to the front, please rewrite it");
+    }
+
+    #[test]
+    fn solve_decides_closed_formulas() {
+        assert_eq!(solve("forall x. exists y. x ^ y").is_some(), true);
+        assert_eq!(solve("exists y. forall x. x ^ y").is_some(), false);
+        assert_eq!(solve("forall x, y. exists z. z = (x & y)").is_some(), true);
+        assert_eq!(solve("forall x, y. exists z. exactly(1; x, y, z)").is_some(), false);
+        assert_eq!(solve("forall x, y. exists z. atmost(1; x, y, z)").is_some(), false);
+        assert_eq!(solve("forall x. exists y, z. maj(x, y, z) & !(y & z)").is_some(), false);
+        assert_eq!(solve("forall x. exists y, z. maj(x, y, z) & !(x & y & z)").is_some(), true);
+        assert_eq!(solve("!forall x. x").is_some(), true);
+    }
+
+    #[test]
+    fn solve_returns_witness_for_free_variables() {
+        let witness = solve("a & !b & forall x. x | a").unwrap();
+        assert_eq!(witness.get("a"), Some(&true));
+        assert_eq!(witness.get("b"), Some(&false));
+
+        assert_eq!(solve("a & forall x. x & a").is_some(), false);
+    }
+
+    #[test]
+    fn solve_agrees_with_expansion() {
+        let expressions = ["forall x. exists y. (x => y) & (a | x = y)",
+            "exists x. forall y. parity(x, y, a) | y & b",
+            "forall x. (a ? x : b) | exists y. y & !x",
+            "!(exists x. forall y. x !^ y) & (a !=> b)"];
+        for str in expressions.iter() {
+            let expr = parse_str(str);
+            let mut ctxt = EvaluationContext::new(free_variables(expr.as_ref()));
+            for values in 0..(1 << ctxt.variables.len()) {
+                ctxt.set_not_presets(values);
+                let bindings: BTreeMap<String, Box<dyn Expression>> = ctxt.variables.iter()
+                    .map(|var| (var.clone(), Box::new(Value::new(ctxt.get(var))) as Box<dyn Expression>))
+                    .collect();
+                let closed = expr.substitute(&bindings);
+                assert_eq!(Qbf::from_expression(closed.as_ref()).unwrap().solve().is_some(), expr.eval(&ctxt), "{}", str);
+            }
+        }
+    }
+
+    #[test]
+    fn to_qdimacs_prints_prefix_and_clauses() {
+        let qbf = Qbf::from_expression(parse_str("forall x. exists y. (x | y) & (!x | !y)").as_ref()).unwrap();
+        assert_eq!(qbf.to_qdimacs(), "c var 1 x\nc var 2 y\np cnf 2 2\na 1 0\ne 2 0\n1 2 0\n-1 -2 0\n");
+    }
+
+    #[test]
+    fn from_qdimacs_reads_formulas() {
+        let expr = from_qdimacs("c example\nc var 1 x\np cnf 3 2\na 1 0\ne 2 0\n1 2 -3 0\n-1\n-2 0\n").unwrap();
+        assert_eq!(expr.to_string(), "forall x. exists x2. (x | (x2 | !x3)) & (!x | !x2)");
+
+        let result = from_qdimacs("1 2 0\n");
+        assert_eq!(result.is_err(), true);
+        assert_eq!(result.err().unwrap(), "line 1: problem line expected, but got '1'");
+
+        let result = from_qdimacs("p cnf 1 1\n1 a 0\n");
+        assert_eq!(result.is_err(), true);
+        assert_eq!(result.err().unwrap(), "line 2: invalid literal 'a'");
+    }
+
+    #[test]
+    fn qdimacs_round_trip_preserves_truth() {
+        let qbf = Qbf::from_expression(parse_str("forall x. exists y, z. exactly(1; x, y, z)").as_ref()).unwrap();
+        let expr = from_qdimacs(&qbf.to_qdimacs()).unwrap();
+        assert_eq!(Qbf::from_expression(expr.as_ref()).unwrap().solve().is_some(), true);
+    }
+}
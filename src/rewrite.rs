@@ -0,0 +1,292 @@
+use std::collections::BTreeMap;
+
+use crate::expression::{Expression, find_free_occurrence, free_variables, replace_child};
+use crate::parser::{parse_with_options, ParseOptions};
+use crate::simplify::same;
+use crate::tokens::{Diagnostic, ErrorCode, ParseError, Token, tokenize_all};
+
+/// A rewrite rule `<pattern> -> <replacement>`, e.g. `?x & (?x | ?y) -> ?x`. Pattern variables
+/// start with `?` and match any subexpression; all occurrences of a pattern variable have to match
+/// the same subexpression.
+pub struct Rule {
+    pub text: String,
+    pub pattern: Box<dyn Expression>,
+    pub replacement: Box<dyn Expression>,
+}
+
+/// The order in which the subexpressions are tried: `Innermost` rewrites the subexpressions
+/// before the expression itself, `Outermost` the expression first.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Strategy {
+    Innermost,
+    Outermost,
+}
+
+/// The outcome of applying rewrite rules.
+pub struct Rewriting {
+    pub expr: Box<dyn Expression>,
+    /// How often each rule fired, in the order of the rules
+    pub fired: Vec<usize>,
+    pub steps: usize,
+    /// Whether no rule applies to the result, i.e. the maximum number of steps was not reached
+    pub complete: bool,
+}
+
+impl Rule {
+    /// Parses a rule; within a rule `?` directly followed by a name is a pattern variable and
+    /// `->` separates the pattern from the replacement.
+    pub fn parse(str: &str, options: &ParseOptions, warnings: &mut Vec<Diagnostic>) -> Result<Rule, Vec<Diagnostic>> {
+        let (tokens, mut errors) = tokenize_all(str);
+
+        // The tokenizer reports `->` as a misspelled `=>`, here it is the arrow of the rule; an
+        // arrow in a comment or a quoted name is no token
+        let chars: Vec<char> = str.chars().collect();
+        let arrows: Vec<usize> = tokens.iter()
+            .filter_map(|token| match token {
+                Token::Operator(pos, op) if op == "=>" && chars[*pos] == '-' => Some(*pos),
+                _ => None
+            })
+            .collect();
+        errors.retain(|err| !arrows.contains(&err.error.pos));
+        let arrow = match arrows.as_slice() {
+            [arrow] => *arrow,
+            [] => return Err(vec![rule_error(chars.len(), 0, "`->` expected between pattern and replacement")]),
+            [_, second, ..] => return Err(vec![rule_error(*second, 2, "a rule contains only one `->`")])
+        };
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let tokens = merge_pattern_variables(tokens);
+        let split = tokens.iter().position(|token| matches!(token, Token::Operator(pos, _) if *pos == arrow)).unwrap();
+        if split == 0 {
+            return Err(vec![rule_error(arrow, 2, "pattern expected in front of `->`")]);
+        }
+        if split == tokens.len() - 1 {
+            return Err(vec![rule_error(arrow + 2, 0, "replacement expected after `->`")]);
+        }
+        let pattern = parse_with_options(&tokens[..split], options, warnings);
+        let replacement = parse_with_options(&tokens[(split + 1)..], options, warnings);
+        let (pattern, replacement) = match (pattern, replacement) {
+            (Ok(pattern), Ok(replacement)) => (pattern, replacement),
+            (pattern, replacement) => {
+                let mut errors = pattern.err().unwrap_or_default();
+                errors.extend(replacement.err().unwrap_or_default());
+                return Err(errors);
+            }
+        };
+
+        let known = free_variables(pattern.as_ref());
+        let unknown: Vec<Diagnostic> = free_variables(replacement.as_ref()).iter()
+            .filter(|var| is_pattern_variable(var) && !known.contains(*var))
+            .map(|var| {
+                let message = format!("pattern variable '{}' does not occur in the pattern", var);
+                match find_free_occurrence(replacement.as_ref(), var).and_then(|occurrence| occurrence.range()) {
+                    Some(range) => range.diagnostic(ErrorCode::InvalidRule, &message),
+                    None => rule_error(arrow + 2, 0, &message)
+                }
+            })
+            .collect();
+        if !unknown.is_empty() {
+            return Err(unknown);
+        }
+        Ok(Rule { text: str.trim().to_string(), pattern, replacement })
+    }
+
+    /// Rewrites the expression if it matches the pattern.
+    pub fn apply(&self, expr: &dyn Expression) -> Option<Box<dyn Expression>> {
+        let mut bindings = BTreeMap::new();
+        if matches(self.pattern.as_ref(), expr, &mut bindings) {
+            Some(self.replacement.substitute(&bindings))
+        } else {
+            None
+        }
+    }
+}
+
+fn rule_error(pos: usize, len: usize, message: &str) -> Diagnostic {
+    ParseError { code: ErrorCode::InvalidRule, pos, len, message: String::from(message) }.into()
+}
+
+/// Joins a `?` and a directly following name to a pattern variable.
+fn merge_pattern_variables(tokens: Vec<Token>) -> Vec<Token> {
+    let mut result: Vec<Token> = Vec::new();
+    for token in tokens {
+        match (result.last(), &token) {
+            (Some(Token::Operator(pos, op)), Token::Variable(name_pos, name)) if op == "?" && *name_pos == pos + 1 => {
+                let pos = *pos;
+                result.pop();
+                result.push(Token::Variable(pos, format!("?{}", name)));
+            }
+            _ => result.push(token)
+        }
+    }
+    result
+}
+
+fn is_pattern_variable(name: &str) -> bool {
+    name.starts_with('?')
+}
+
+/// Checks whether the expression matches the pattern, binding the pattern variables.
+fn matches(pattern: &dyn Expression, expr: &dyn Expression, bindings: &mut BTreeMap<String, Box<dyn Expression>>) -> bool {
+    if let Some(var) = pattern.as_variable().filter(|var| is_pattern_variable(&var.name)) {
+        return match bindings.get(&var.name) {
+            Some(bound) => same(bound.as_ref(), expr),
+            None => {
+                bindings.insert(var.name.clone(), expr.clone_box());
+                true
+            }
+        };
+    }
+    let same_node = if let (Some(p), Some(e)) = (pattern.as_value(), expr.as_value()) {
+        p.value == e.value
+    } else if let (Some(p), Some(e)) = (pattern.as_variable(), expr.as_variable()) {
+        p.name == e.name
+    } else if let (Some(p), Some(e)) = (pattern.as_unary(), expr.as_unary()) {
+        p.op == e.op
+    } else if let (Some(p), Some(e)) = (pattern.as_binary(), expr.as_binary()) {
+        p.op == e.op
+    } else if let (Some(p), Some(e)) = (pattern.as_cardinality(), expr.as_cardinality()) {
+        p.op == e.op && p.k == e.k && p.args.len() == e.args.len()
+    } else if let (Some(p), Some(e)) = (pattern.as_quantified(), expr.as_quantified()) {
+        p.quantifier == e.quantifier && p.var == e.var
+    } else {
+        pattern.as_conditional().is_some() && expr.as_conditional().is_some()
+    };
+    same_node && pattern.children().iter().zip(expr.children()).all(|(p, e)| matches(*p, e, bindings))
+}
+
+/*
+ * Rewriting
+ */
+
+/// Applies the rules until none of them applies anymore or `max_steps` rules fired. Each step
+/// rewrites the first subexpression in the order of the strategy, the rules are tried in their
+/// order.
+pub fn apply_rules(expr: &dyn Expression, rules: &[Rule], strategy: Strategy, max_steps: usize) -> Rewriting {
+    let mut result = Rewriting { expr: expr.clone_box(), fired: vec![0; rules.len()], steps: 0, complete: false };
+    while result.steps < max_steps {
+        match step(result.expr.as_ref(), rules, strategy) {
+            Some((rule, expr)) => {
+                result.fired[rule] += 1;
+                result.steps += 1;
+                result.expr = expr;
+            }
+            None => {
+                result.complete = true;
+                return result;
+            }
+        }
+    }
+    result.complete = step(result.expr.as_ref(), rules, strategy).is_none();
+    result
+}
+
+/// Applies a single rule, returns the index of the rule and the result.
+fn step(expr: &dyn Expression, rules: &[Rule], strategy: Strategy) -> Option<(usize, Box<dyn Expression>)> {
+    let at_top = || rules.iter().enumerate().find_map(|(index, rule)| rule.apply(expr).map(|result| (index, result)));
+    if strategy == Strategy::Outermost {
+        if let Some(result) = at_top() {
+            return Some(result);
+        }
+    }
+    for (index, child) in expr.children().into_iter().enumerate() {
+        if let Some((rule, rewritten)) = step(child, rules, strategy) {
+            return Some((rule, replace_child(expr, index, rewritten)));
+        }
+    }
+    if strategy == Strategy::Innermost {
+        return at_top();
+    }
+    None
+}
+
+/*
+ * Tests
+ */
+
+#[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+    use crate::tokens::tokenize;
+
+    fn rule(str: &str) -> Rule {
+        Rule::parse(str, &ParseOptions::default(), &mut Vec::new()).ok().unwrap()
+    }
+
+    fn rewritten(str: &str, rules: &[Rule], strategy: Strategy, max_steps: usize) -> Rewriting {
+        let expr = parse(&tokenize(str).unwrap_or_else(|_| vec![])).ok().unwrap();
+        apply_rules(expr.as_ref(), rules, strategy, max_steps)
+    }
+
+    #[test]
+    fn rule_parse_reads_pattern_variables() {
+        let rule = rule("?x & (?x | ?y) -> ?x");
+        assert_eq!(rule.pattern.to_dump_string(), "And(Variable(?x),Or(Variable(?x),Variable(?y)))");
+        assert_eq!(rule.replacement.to_dump_string(), "Variable(?x)");
+        assert_eq!(rule.text, "?x & (?x | ?y) -> ?x");
+
+        // A `?` followed by a space is still a conditional
+        let rule = super::tests::rule("?s ? ?a : 0 -> ?s & ?a");
+        assert_eq!(rule.pattern.to_dump_string(), "Ite(Variable(?s),Variable(?a),Value(0))");
+    }
+
+    #[test]
+    fn rule_parse_returns_err_for_invalid_rules() {
+        let error = |str: &str| {
+            let errors = Rule::parse(str, &ParseOptions::default(), &mut Vec::new()).err().unwrap();
+            (errors[0].error.message.clone(), errors[0].error.pos)
+        };
+        assert_eq!(error("?x & 1"), (String::from("`->` expected between pattern and replacement"), 6));
+        assert_eq!(error("?x -> ?x -> 1"), (String::from("a rule contains only one `->`"), 9));
+        assert_eq!(error("-> 1"), (String::from("pattern expected in front of `->`"), 0));
+        assert_eq!(error("?x ->"), (String::from("replacement expected after `->`"), 5));
+        assert_eq!(error("?x -> ?x | ?y"), (String::from("pattern variable '?y' does not occur in the pattern"), 11));
+        assert_eq!(error("?x & -> 1"), (String::from("missing right hand side operand"), 4));
+        assert_eq!(Rule::parse("?x <-> 1 -> 1", &ParseOptions::default(), &mut Vec::new()).is_err(), true);
+    }
+
+    #[test]
+    fn rule_parse_ignores_arrows_in_comments_and_quoted_names() {
+        let errors = Rule::parse("?x & 1 /* -> */", &ParseOptions::default(), &mut Vec::new()).err().unwrap();
+        assert_eq!(errors[0].error.message, "`->` expected between pattern and replacement");
+
+        let rule = rule("\"p->q\" & ?x -> ?x");
+        assert_eq!(rule.pattern.to_dump_string(), "And(Variable(p->q),Variable(?x))");
+        assert_eq!(rule.replacement.to_dump_string(), "Variable(?x)");
+    }
+
+    #[test]
+    fn apply_rules_reports_fired_rules() {
+        let rules = [rule("?x & (?x | ?y) -> ?x"), rule("!!?x -> ?x"), rule("?x & 1 -> ?x")];
+        let result = rewritten("!!(a & (a | b)) | c & (c | !!d)", &rules, Strategy::Innermost, 100);
+        assert_eq!(result.expr.to_string(), "a | c");
+        assert_eq!(result.fired, vec![2, 2, 0]);
+        assert_eq!(result.steps, 4);
+        assert_eq!(result.complete, true);
+
+        // All occurrences of a pattern variable match the same expression
+        let result = rewritten("a & (b | c)", &rules, Strategy::Innermost, 100);
+        assert_eq!(result.expr.to_string(), "a & (b | c)");
+        assert_eq!(result.fired, vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn apply_rules_follows_the_strategy() {
+        let rules = [rule("?x & ?y -> ?y")];
+        assert_eq!(rewritten("(a & b) & c", &rules, Strategy::Outermost, 1).expr.to_string(), "c");
+        assert_eq!(rewritten("(a & b) & c", &rules, Strategy::Innermost, 1).expr.to_string(), "b & c");
+    }
+
+    #[test]
+    fn apply_rules_stops_after_max_steps() {
+        let rules = [rule("?x -> !!?x")];
+        let result = rewritten("a", &rules, Strategy::Outermost, 3);
+        assert_eq!(result.expr.to_string(), "!(!(!(!(!(!a)))))");
+        assert_eq!(result.steps, 3);
+        assert_eq!(result.complete, false);
+    }
+}
@@ -0,0 +1,316 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::expression::{BinaryExpression, BinaryOperator, CardinalityExpression, CardinalityOperator, ConditionalExpression, EvaluationContext, Expression, free_variables, QuantifiedExpression, UnaryExpression, UnaryOperator, Value};
+
+/*
+ * Simplification
+ */
+
+/// Simplifies the expression until nothing changes anymore, applying constant folding, identity
+/// and annihilator, idempotence, complement, absorption and double negation laws; negations are
+/// moved towards the variables by De Morgan's laws. `!&`, `!|`, `!^`, `<=` and `!=>` are replaced
+/// by the basic operators.
+pub fn simplify(expr: &dyn Expression) -> Box<dyn Expression> {
+    let mut result = simplify_once(expr);
+    loop {
+        let next = simplify_once(result.as_ref());
+        if next.to_string() == result.to_string() {
+            return next;
+        }
+        result = next;
+    }
+}
+
+/// Substitutes the preset values into the expression and simplifies it, the result is the
+/// residual expression over the remaining variables (the cofactor).
+pub fn residual(expr: &dyn Expression, presets: &BTreeMap<String, bool>) -> Box<dyn Expression> {
+    let bindings: BTreeMap<String, Box<dyn Expression>> = presets.iter()
+        .map(|(var, value)| (var.clone(), Box::new(Value::new(*value)) as Box<dyn Expression>))
+        .collect();
+    simplify(expr.substitute(&bindings).as_ref())
+}
+
+fn simplify_once(expr: &dyn Expression) -> Box<dyn Expression> {
+    if let Some(unary) = expr.as_unary() {
+        match unary.op {
+            UnaryOperator::NEG => negate(simplify_once(unary.arg.as_ref()))
+        }
+    } else if let Some(binary) = expr.as_binary() {
+        combine(binary.op, simplify_once(binary.left.as_ref()), simplify_once(binary.right.as_ref()))
+    } else if let Some(conditional) = expr.as_conditional() {
+        choose(simplify_once(conditional.condition.as_ref()), simplify_once(conditional.if_true.as_ref()), simplify_once(conditional.if_false.as_ref()))
+    } else if let Some(cardinality) = expr.as_cardinality() {
+        let args: Vec<Box<dyn Expression>> = cardinality.args.iter().map(|arg| simplify_once(arg.as_ref())).collect();
+        let folded = CardinalityExpression::new(cardinality.op, cardinality.k, args);
+        match decide(&folded) {
+            Some(value) => Box::new(Value::new(value)),
+            None => Box::new(folded)
+        }
+    } else if let Some(quantified) = expr.as_quantified() {
+        let body = simplify_once(quantified.body.as_ref());
+        if free_variables(body.as_ref()).contains(&quantified.var) {
+            Box::new(QuantifiedExpression::new(quantified.quantifier, &quantified.var, body))
+        } else {
+            body
+        }
+    } else {
+        expr.clone_box()
+    }
+}
+
+/// Returns the value of a counting function if the constant arguments already decide it, e.g.
+/// `maj(a, 1, 1)` is `1` whatever `a` is.
+fn decide(cardinality: &CardinalityExpression) -> Option<bool> {
+    let values: Vec<bool> = cardinality.args.iter().filter_map(|arg| arg.as_value()).map(|value| value.value).collect();
+    let count = cardinality.args.len();
+    if values.len() == count {
+        return Some(cardinality.eval(&EvaluationContext::new(BTreeSet::new())));
+    }
+    // The number of true arguments is between `ones` and `ones + unknown`
+    let ones = values.iter().filter(|value| **value).count();
+    let most = ones + count - values.len();
+    let at_least = |k: usize| if ones >= k { Some(true) } else if most < k { Some(false) } else { None };
+    match cardinality.op {
+        CardinalityOperator::MAJ => at_least(count / 2 + 1),
+        CardinalityOperator::ATLEAST => at_least(cardinality.k),
+        CardinalityOperator::ATMOST => at_least(cardinality.k + 1).map(|value| !value),
+        CardinalityOperator::EXACTLY if ones > cardinality.k || most < cardinality.k => Some(false),
+        _ => None
+    }
+}
+
+/// Returns the negation of a simplified expression, the negation is moved inwards.
+fn negate(expr: Box<dyn Expression>) -> Box<dyn Expression> {
+    if let Some(value) = expr.as_value() {
+        return Box::new(Value::new(!value.value));
+    }
+    if let Some(unary) = expr.as_unary() {
+        return unary.arg.clone_box();
+    }
+    if let Some(binary) = expr.as_binary() {
+        let (left, right) = (binary.left.clone_box(), binary.right.clone_box());
+        match binary.op {
+            BinaryOperator::AND => return combine(BinaryOperator::OR, negate(left), negate(right)),
+            BinaryOperator::OR => return combine(BinaryOperator::AND, negate(left), negate(right)),
+            BinaryOperator::IMP => return combine(BinaryOperator::AND, left, negate(right)),
+            BinaryOperator::XOR => return combine(BinaryOperator::EQ, left, right),
+            BinaryOperator::EQ => return combine(BinaryOperator::XOR, left, right),
+            _ => {}
+        }
+    }
+    Box::new(UnaryExpression::new(UnaryOperator::NEG, expr))
+}
+
+/// Combines two simplified operands by the operator.
+fn combine(op: BinaryOperator, left: Box<dyn Expression>, right: Box<dyn Expression>) -> Box<dyn Expression> {
+    let value = |expr: &dyn Expression| expr.as_value().map(|value| value.value);
+    match op {
+        BinaryOperator::AND | BinaryOperator::OR => {
+            let mut operands = Vec::new();
+            flatten(op, left, &mut operands);
+            flatten(op, right, &mut operands);
+            junction(op, operands)
+        }
+        BinaryOperator::NAND => negate(combine(BinaryOperator::AND, left, right)),
+        BinaryOperator::NOR => negate(combine(BinaryOperator::OR, left, right)),
+        BinaryOperator::XNOR => combine(BinaryOperator::EQ, left, right),
+        BinaryOperator::RIMP => combine(BinaryOperator::IMP, right, left),
+        BinaryOperator::NIMP => combine(BinaryOperator::AND, left, negate(right)),
+        BinaryOperator::XOR | BinaryOperator::EQ => {
+            // `a ^ b` is `a = !b`, so both are handled alike
+            let equal = op == BinaryOperator::EQ;
+            match (value(left.as_ref()), value(right.as_ref())) {
+                (Some(l), Some(r)) => Box::new(Value::new((l == r) == equal)),
+                (Some(l), None) => if l == equal { right } else { negate(right) },
+                (None, Some(r)) => if r == equal { left } else { negate(left) },
+                _ if same(left.as_ref(), right.as_ref()) => Box::new(Value::new(equal)),
+                _ if complementary(left.as_ref(), right.as_ref()) => Box::new(Value::new(!equal)),
+                _ => Box::new(BinaryExpression::new(op, left, right))
+            }
+        }
+        BinaryOperator::IMP => match (value(left.as_ref()), value(right.as_ref())) {
+            (Some(false), _) | (_, Some(true)) => Box::new(Value::new(true)),
+            (Some(true), _) => right,
+            (_, Some(false)) => negate(left),
+            _ if same(left.as_ref(), right.as_ref()) => Box::new(Value::new(true)),
+            _ if complementary(left.as_ref(), right.as_ref()) => right,
+            _ => Box::new(BinaryExpression::new(op, left, right))
+        }
+    }
+}
+
+/// Simplifies `condition ? if_true : if_false` for simplified operands.
+fn choose(condition: Box<dyn Expression>, if_true: Box<dyn Expression>, if_false: Box<dyn Expression>) -> Box<dyn Expression> {
+    if let Some(value) = condition.as_value() {
+        return if value.value { if_true } else { if_false };
+    }
+    if same(if_true.as_ref(), if_false.as_ref()) {
+        return if_true;
+    }
+    match (if_true.as_value().map(|value| value.value), if_false.as_value().map(|value| value.value)) {
+        (Some(true), _) => combine(BinaryOperator::OR, condition, if_false),
+        (Some(false), _) => combine(BinaryOperator::AND, negate(condition), if_false),
+        (_, Some(true)) => combine(BinaryOperator::OR, negate(condition), if_true),
+        (_, Some(false)) => combine(BinaryOperator::AND, condition, if_true),
+        _ => Box::new(ConditionalExpression::new(condition, if_true, if_false))
+    }
+}
+
+/// Collects the operands of a chain of `op`.
+pub fn flatten(op: BinaryOperator, expr: Box<dyn Expression>, operands: &mut Vec<Box<dyn Expression>>) {
+    match expr.as_binary() {
+        Some(binary) if binary.op == op => {
+            flatten(op, binary.left.clone_box(), operands);
+            flatten(op, binary.right.clone_box(), operands);
+        }
+        _ => operands.push(expr)
+    }
+}
+
+/// Simplifies the conjunction or disjunction of the operands.
+fn junction(op: BinaryOperator, operands: Vec<Box<dyn Expression>>) -> Box<dyn Expression> {
+    // The neutral element of the operator, the other value annihilates it
+    let neutral = op == BinaryOperator::AND;
+    let dual = if neutral { BinaryOperator::OR } else { BinaryOperator::AND };
+
+    let mut result: Vec<Box<dyn Expression>> = Vec::new();
+    for operand in operands {
+        match operand.as_value() {
+            Some(value) if value.value == neutral => continue,
+            Some(_) => return Box::new(Value::new(!neutral)),
+            None => {}
+        }
+        if result.iter().any(|known| complementary(known.as_ref(), operand.as_ref())) {
+            return Box::new(Value::new(!neutral));
+        }
+        if !result.iter().any(|known| same(known.as_ref(), operand.as_ref())) {
+            result.push(operand);
+        }
+    }
+
+    // Absorption: `a & (a | b)` is `a`
+    let absorbed: Vec<bool> = result.iter().map(|operand| {
+        let mut parts = Vec::new();
+        flatten(dual, operand.clone_box(), &mut parts);
+        parts.len() > 1 && result.iter().any(|other| parts.iter().any(|part| same(part.as_ref(), other.as_ref())))
+    }).collect();
+    let mut kept = result.into_iter().zip(absorbed).filter(|(_, absorbed)| !absorbed).map(|(operand, _)| operand);
+
+    match kept.next() {
+        Some(first) => kept.fold(first, |left, right| Box::new(BinaryExpression::new(op, left, right))),
+        None => Box::new(Value::new(neutral))
+    }
+}
+
+/// Returns whether both expressions are written alike.
+pub fn same(left: &dyn Expression, right: &dyn Expression) -> bool {
+    left.to_string() == right.to_string()
+}
+
+/// Returns whether one of the expressions is the negation of the other.
+pub fn complementary(left: &dyn Expression, right: &dyn Expression) -> bool {
+    let negated = |expr: &dyn Expression, other: &dyn Expression| expr.as_unary().is_some_and(|unary| same(unary.arg.as_ref(), other));
+    negated(left, right) || negated(right, left)
+}
+
+/*
+ * Tests
+ */
+
+#[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
+mod tests {
+    use super::*;
+    use crate::expression::assert_equivalent;
+    use crate::parser::parse;
+    use crate::tokens::tokenize;
+
+    fn simplified(str: &str) -> String {
+        let expr = parse(&tokenize(str).unwrap_or_else(|_| vec![])).ok().unwrap();
+        let result = simplify(expr.as_ref());
+
+        // The result must be equivalent to the expression
+        assert_equivalent(expr.as_ref(), result.as_ref());
+        result.to_string()
+    }
+
+    #[test]
+    fn simplify_folds_constants() {
+        assert_eq!(simplified("1 & 0 | !0"), "1");
+        assert_eq!(simplified("(1 ^ 1) = 0"), "1");
+        assert_eq!(simplified("0 ? a : b"), "b");
+        assert_eq!(simplified("atleast(2; 1, 0, 1)"), "1");
+        assert_eq!(simplified("maj(a, 1, 1)"), "1");
+        assert_eq!(simplified("maj(a, b, 0, 0)"), "0");
+        assert_eq!(simplified("atleast(3; a)"), "0");
+        assert_eq!(simplified("atleast(0; a, b)"), "1");
+        assert_eq!(simplified("atmost(1; a, 1, 1)"), "0");
+        assert_eq!(simplified("atmost(2; a, b)"), "1");
+        assert_eq!(simplified("exactly(1; a, 1, 1)"), "0");
+        assert_eq!(simplified("exactly(1; a, b, 1)"), "exactly(1; a, b, 1)");
+        assert_eq!(simplified("parity(a, 1)"), "parity(a, 1)");
+    }
+
+    #[test]
+    fn simplify_applies_identity_and_annihilator() {
+        assert_eq!(simplified("a & 1 | 0"), "a");
+        assert_eq!(simplified("a & 0 | b"), "b");
+        assert_eq!(simplified("a | 1"), "1");
+        assert_eq!(simplified("a ^ 1"), "!a");
+        assert_eq!(simplified("a = 0"), "!a");
+        assert_eq!(simplified("a => 0"), "!a");
+        assert_eq!(simplified("1 => a"), "a");
+        assert_eq!(simplified("s ? 1 : b"), "s | b");
+    }
+
+    #[test]
+    fn simplify_applies_idempotence_and_complement() {
+        assert_eq!(simplified("a & 1 | a & !a"), "a");
+        assert_eq!(simplified("a & b & a"), "a & b");
+        assert_eq!(simplified("a | b | !a"), "1");
+        assert_eq!(simplified("a ^ a"), "0");
+        assert_eq!(simplified("a = !a"), "0");
+        assert_eq!(simplified("a => a"), "1");
+    }
+
+    #[test]
+    fn simplify_applies_absorption() {
+        assert_eq!(simplified("a & (a | b)"), "a");
+        assert_eq!(simplified("(b & a) | c | a"), "c | a");
+        assert_eq!(simplified("(a | b) & (c | b) & b"), "b");
+    }
+
+    #[test]
+    fn simplify_moves_negations_inwards() {
+        assert_eq!(simplified("!!a"), "a");
+        assert_eq!(simplified("!(a & !b)"), "!a | b");
+        assert_eq!(simplified("!(a | (b => c))"), "(!a & b) & !c");
+        assert_eq!(simplified("a !& b"), "!a | !b");
+        assert_eq!(simplified("!(a ^ b)"), "a = b");
+        assert_eq!(simplified("a <= b"), "b => a");
+    }
+
+    #[test]
+    fn residual_substitutes_presets() {
+        let expr = parse(&tokenize("(a & c | b & !c) ^ d").unwrap_or_else(|_| vec![])).ok().unwrap();
+        let mut presets = BTreeMap::new();
+        presets.insert(String::from("c"), true);
+        assert_eq!(residual(expr.as_ref(), &presets).to_string(), "a ^ d");
+        presets.insert(String::from("d"), true);
+        assert_eq!(residual(expr.as_ref(), &presets).to_string(), "!a");
+        presets.insert(String::from("a"), false);
+        assert_eq!(residual(expr.as_ref(), &presets).to_string(), "1");
+
+        // Bound variables are not replaced
+        let expr = parse(&tokenize("exists c. c & a").unwrap_or_else(|_| vec![])).ok().unwrap();
+        let mut presets = BTreeMap::new();
+        presets.insert(String::from("c"), false);
+        assert_eq!(residual(expr.as_ref(), &presets).to_string(), "exists c. c & a");
+    }
+
+    #[test]
+    fn simplify_removes_unused_quantifiers() {
+        assert_eq!(simplified("exists x. a | x & 0"), "a");
+        assert_eq!(simplified("forall x. !!x | a"), "forall x. x | a");
+    }
+}
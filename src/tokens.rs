@@ -2,9 +2,14 @@
 pub enum Token {
     Value(usize, bool),
     Variable(usize, String),
+    QuotedVariable(usize, String),
     Operator(usize, String),
     OpenParanthesis(usize),
     CloseParanthesis(usize),
+    Comma(usize),
+    Semicolon(usize),
+    Number(usize, String),
+    Dot(usize),
 }
 
 impl PartialEq for Token {
@@ -22,6 +27,12 @@ impl PartialEq for Token {
                     _ => false
                 }
             }
+            Token::QuotedVariable(spos, sname) => {
+                match other {
+                    Token::QuotedVariable(opos, oname) => spos == opos && sname == oname,
+                    _ => false
+                }
+            }
             Token::Operator(spos, sname) => {
                 match other {
                     Token::Operator(opos, oname) => spos == opos && sname == oname,
@@ -40,104 +51,442 @@ impl PartialEq for Token {
                     _ => false
                 }
             }
+            Token::Comma(spos) => {
+                match other {
+                    Token::Comma(opos) => spos == opos,
+                    _ => false
+                }
+            }
+            Token::Semicolon(spos) => {
+                match other {
+                    Token::Semicolon(opos) => spos == opos,
+                    _ => false
+                }
+            }
+            Token::Number(spos, svalue) => {
+                match other {
+                    Token::Number(opos, ovalue) => spos == opos && svalue == ovalue,
+                    _ => false
+                }
+            }
+            Token::Dot(spos) => {
+                match other {
+                    Token::Dot(opos) => spos == opos,
+                    _ => false
+                }
+            }
+        }
+    }
+}
+
+/// Classifies parse errors, each kind has a stable code such as `E0004`; codes of warnings start
+/// with `W`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorCode {
+    InvalidCharacter,
+    InvalidToken,
+    MissingInput,
+    UnexpectedToken,
+    MissingOperand,
+    InvalidCall,
+    InvalidDefinition,
+    InvalidRule,
+    AmbiguousChain,
+    FreeAndBound,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::InvalidCharacter => "E0001",
+            ErrorCode::InvalidToken => "E0002",
+            ErrorCode::MissingInput => "E0003",
+            ErrorCode::UnexpectedToken => "E0004",
+            ErrorCode::MissingOperand => "E0005",
+            ErrorCode::InvalidCall => "E0006",
+            ErrorCode::InvalidDefinition => "E0007",
+            ErrorCode::InvalidRule => "E0008",
+            ErrorCode::AmbiguousChain => "W0001",
+            ErrorCode::FreeAndBound => "W0002",
         }
     }
+
+    pub fn is_warning(&self) -> bool {
+        self.as_str().starts_with('W')
+    }
 }
 
 #[derive(Debug)]
 pub struct ParseError {
+    pub code: ErrorCode,
+    pub message: String,
+    pub pos: usize,
+    pub len: usize,
+}
+
+/// A parse error together with hints that help to fix it.
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub error: ParseError,
+    /// Related parts of the source, e.g. where an unbalanced `(` was opened
+    pub labels: Vec<Label>,
+    /// A suggestion such as "did you mean `=>`?"
+    pub help: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Label {
     pub message: String,
     pub pos: usize,
     pub len: usize,
 }
 
+impl From<ParseError> for Diagnostic {
+    fn from(error: ParseError) -> Diagnostic {
+        Diagnostic { error, labels: Vec::new(), help: None }
+    }
+}
+
+/// The characters of the source an expression was parsed from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SourceRange {
+    pub pos: usize,
+    pub len: usize,
+}
+
+impl SourceRange {
+    /// Returns the range from the start of this range to the end of the other one.
+    pub fn join(&self, other: &SourceRange) -> SourceRange {
+        SourceRange { pos: self.pos, len: (other.pos + other.len).max(self.pos) - self.pos }
+    }
+
+    pub fn label(&self, message: &str) -> Label {
+        Label { message: String::from(message), pos: self.pos, len: self.len }
+    }
+
+    /// Returns a diagnostic underlining this range, e.g. for an analysis of a parsed expression.
+    pub fn diagnostic(&self, code: ErrorCode, message: &str) -> Diagnostic {
+        ParseError { code, message: String::from(message), pos: self.pos, len: self.len }.into()
+    }
+}
+
+/// A 1-based line and column within the source.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// The source range from `start` (inclusive) to `end` (exclusive).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Span {
+    pub start: Location,
+    pub end: Location,
+}
+
+impl ParseError {
+    /// Returns the lines and columns the error covers within the given source.
+    pub fn span(&self, str: &str) -> Span {
+        let (line, column) = line_column(str, self.pos);
+        let start = Location { line, column };
+        let (line, column) = line_column(str, self.pos + self.len);
+        Span { start, end: Location { line, column } }
+    }
+}
+
+/// Converts a char index into a 1-based line and column; lines are separated by `\n`.
+pub fn line_column(str: &str, pos: usize) -> (usize, usize) {
+    let mut line = 1;
+    let mut column = 1;
+    for ch in str.chars().take(pos) {
+        if ch == '\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        }
+    }
+    (line, column)
+}
 
 pub fn token_pos(token: &Token) -> usize {
     match token {
         Token::Value(pos, _) => *pos,
         Token::Variable(pos, _) => *pos,
+        Token::QuotedVariable(pos, _) => *pos,
         Token::Operator(pos, _) => *pos,
         Token::OpenParanthesis(pos) => *pos,
         Token::CloseParanthesis(pos) => *pos,
+        Token::Comma(pos) => *pos,
+        Token::Semicolon(pos) => *pos,
+        Token::Number(pos, _) => *pos,
+        Token::Dot(pos) => *pos,
     }
 }
 
 pub fn token_len(token: &Token) -> usize {
     match token {
         Token::Value(_, _) => 1,
-        Token::Variable(_, name) => name.len(),
-        Token::Operator(_, name) => name.len(),
+        Token::Variable(_, name) => name.chars().count(),
+        Token::QuotedVariable(_, name) => name.chars().count() + 2,
+        Token::Operator(_, name) => name.chars().count(),
         Token::OpenParanthesis(_) => 1,
         Token::CloseParanthesis(_) => 1,
+        Token::Comma(_) => 1,
+        Token::Semicolon(_) => 1,
+        Token::Number(_, value) => value.len(),
+        Token::Dot(_) => 1,
     }
 }
 
 pub fn token_name(token: &Token) -> &str {
     match token {
         Token::Value(_, value) => if *value { "1" } else { "0" },
-        Token::Operator(_, name) => name,
+        Token::Operator(_, name) => operator_symbol(name),
         Token::Variable(_, name) => name,
+        Token::QuotedVariable(_, name) => name,
         Token::OpenParanthesis(_) => "(",
         Token::CloseParanthesis(_) => ")",
+        Token::Comma(_) => ",",
+        Token::Semicolon(_) => ";",
+        Token::Number(_, value) => value,
+        Token::Dot(_) => ".",
+    }
+}
+
+/// Known operator spellings and the ASCII symbol they stand for; longer spellings come first.
+const OPERATORS: &[(&str, &str)] = &[
+    ("!=>", "!=>"),
+    ("!&", "!&"),
+    ("!|", "!|"),
+    ("!^", "!^"),
+    ("<=", "<="),
+    ("=>", "=>"),
+    ("&", "&"),
+    ("|", "|"),
+    ("^", "^"),
+    ("=", "="),
+    ("!", "!"),
+    ("?", "?"),
+    (":", ":"),
+    ("\u{00ac}", "!"), // ¬
+    ("\u{2227}", "&"), // ∧
+    ("\u{2228}", "|"), // ∨
+    ("\u{2295}", "^"), // ⊕
+    ("\u{2192}", "=>"), // →
+    ("\u{21d2}", "=>"), // ⇒
+    ("\u{2194}", "="), // ↔
+    ("\u{21d4}", "="), // ⇔
+    ("\u{2261}", "="), // ≡
+    ("\u{2191}", "!&"), // ↑
+    ("\u{22bc}", "!&"), // ⊼
+    ("\u{2193}", "!|"), // ↓
+    ("\u{22bd}", "!|"), // ⊽
+    ("\u{2299}", "!^"), // ⊙
+    ("\u{2190}", "<="), // ←
+    ("\u{21d0}", "<="), // ⇐
+    ("\u{219b}", "!=>"), // ↛
+    ("\u{21cf}", "!=>"), // ⇏
+    ("\u{2203}", "exists"), // ∃
+    ("\u{2200}", "forall"), // ∀
+];
+
+/// Operators known from other notations and the operator that is probably meant.
+const MISSPELLINGS: &[(&str, &str)] = &[
+    ("<->", "="),
+    ("<=>", "="),
+    ("->", "=>"),
+    ("&&", "&"),
+    ("||", "|"),
+    ("==", "="),
+    ("!=", "^"),
+    ("~", "!"),
+];
+
+/// Returns the ASCII symbol of the given operator spelling, e.g. `=>` for `→`.
+pub fn operator_symbol(name: &str) -> &str {
+    match OPERATORS.iter().find(|(spelling, _)| *spelling == name) {
+        Some((_, symbol)) => symbol,
+        None => name
     }
 }
 
+#[cfg(test)]
 pub fn tokenize(str: &str) -> Result<Vec<Token>, ParseError> {
+    let (tokens, mut errors) = tokenize_all(str);
+    if errors.is_empty() {
+        Ok(tokens)
+    } else {
+        Err(errors.remove(0).error)
+    }
+}
+
+/// Like `tokenize`, but continues after errors: it returns the tokens found and all errors.
+pub fn tokenize_all(str: &str) -> (Vec<Token>, Vec<Diagnostic>) {
     let mut tokens: Vec<Token> = Vec::new();
-    let mut rest = str;
+    let mut errors: Vec<Diagnostic> = Vec::new();
+    let chars: Vec<char> = str.chars().collect();
     let mut pos: usize = 0;
 
-    while !rest.is_empty() {
+    while pos < chars.len() {
+        let ch = chars[pos];
 
         // Check whitespace
-        if rest.starts_with(" ") || rest.starts_with("\t") {
-            rest = &rest[1..];
+        if ch == ' ' || ch == '\t' || ch == '\n' || ch == '\r' {
             pos += 1;
             continue;
         }
 
+        // Check line comment
+        if ch == '#' {
+            while pos < chars.len() && chars[pos] != '\n' {
+                pos += 1;
+            }
+            continue;
+        }
+
+        // Check block comment
+        if starts_with(&chars[pos..], "/*") {
+            match (pos + 2..chars.len()).find(|end| starts_with(&chars[*end..], "*/")) {
+                Some(end) => pos = end + 2,
+                None => {
+                    errors.push(ParseError { code: ErrorCode::InvalidToken, pos, len: 2, message: String::from("unterminated comment") }.into());
+                    break;
+                }
+            }
+            continue;
+        }
+
         // Check identifier
-        let mut i: usize = 0;
-        while i < rest.len() && char_at(rest, i).is_alphabetic() {
-            i += 1
+        if is_identifier_start(ch) {
+            let mut end = pos + 1;
+            // In the variables bound by a quantifier a `.` ends the list, so `exists x.x` works
+            let bound = is_bound_variable(&tokens);
+            while end < chars.len() && (is_identifier_part(chars[end]) || (!bound && is_inner_dot(&chars, end))) {
+                end += 1
+            }
+            tokens.push(Token::Variable(pos, chars[pos..end].iter().collect()));
+            pos = end;
+            continue;
         }
-        if i > 0 {
-            tokens.push(Token::Variable(pos, String::from(&rest[..i])));
-            rest = &rest[i..];
-            pos += i;
+
+        // Check quoted identifier
+        if ch == '"' {
+            let len = match chars[(pos + 1)..].iter().position(|ch| *ch == '"') {
+                Some(len) => len,
+                None => {
+                    errors.push(ParseError { code: ErrorCode::InvalidToken, pos, len: chars.len() - pos, message: String::from("unterminated quoted name") }.into());
+                    break;
+                }
+            };
+            if len == 0 {
+                errors.push(ParseError { code: ErrorCode::InvalidToken, pos, len: 2, message: String::from("empty quoted name") }.into());
+                pos += 2;
+                continue;
+            }
+            tokens.push(Token::QuotedVariable(pos, chars[(pos + 1)..(pos + 1 + len)].iter().collect()));
+            pos += len + 2;
             continue;
         }
 
-        // Check other literals
-        if rest.starts_with("=>") {
-            tokens.push(Token::Operator(pos, String::from("=>")));
-            rest = &rest[2..];
-            pos += 2;
+        // Check numbers, `0` and `1` are values
+        if ch.is_ascii_digit() {
+            let mut end = pos + 1;
+            while end < chars.len() && chars[end].is_ascii_digit() {
+                end += 1
+            }
+            if end - pos > 1 || ch > '1' {
+                tokens.push(Token::Number(pos, chars[pos..end].iter().collect()));
+                pos = end;
+                continue;
+            }
+        }
+
+        // Check notations of other languages, they are reported but treated like the meant operator
+        if let Some((spelling, meant)) = MISSPELLINGS.iter().find(|(spelling, _)| starts_with(&chars[pos..], spelling)) {
+            if !starts_with(&chars[pos..], "!=>") {
+                let len = spelling.chars().count();
+                errors.push(Diagnostic {
+                    error: ParseError { code: ErrorCode::InvalidToken, pos, len, message: format!("unknown operator '{}'", spelling) },
+                    labels: Vec::new(),
+                    help: Some(format!("did you mean `{}`?", meant)),
+                });
+                tokens.push(Token::Operator(pos, String::from(*meant)));
+                pos += len;
+                continue;
+            }
+        }
+
+        // Check operators
+        if let Some((spelling, _)) = OPERATORS.iter().find(|(spelling, _)| starts_with(&chars[pos..], spelling)) {
+            tokens.push(Token::Operator(pos, String::from(*spelling)));
+            pos += spelling.chars().count();
             continue;
         }
 
         // Any other character
-        let ch = char_at(rest, 0);
         match ch {
             '0' | '1' => tokens.push(Token::Value(pos, ch == '1')),
-            '&' | '|' | '^' | '=' | '!' => tokens.push(Token::Operator(pos, String::from(ch))),
             '(' => tokens.push(Token::OpenParanthesis(pos)),
             ')' => tokens.push(Token::CloseParanthesis(pos)),
-            _ => return Err(ParseError { pos, len: 1, message: String::from(format!("Invalid character '{}'", char_at(rest, 0))) })
+            ',' => tokens.push(Token::Comma(pos)),
+            ';' => tokens.push(Token::Semicolon(pos)),
+            '.' => tokens.push(Token::Dot(pos)),
+            _ => errors.push(ParseError { code: ErrorCode::InvalidCharacter, pos, len: 1, message: format!("Invalid character '{}'", ch) }.into())
         }
-        rest = &rest[1..];
         pos += 1;
     }
 
-    if tokens.is_empty() {
-        return Err(ParseError { message: String::from("no input."), pos: str.len(), len: 0 });
+    if tokens.is_empty() && errors.is_empty() {
+        errors.push(ParseError { code: ErrorCode::MissingInput, message: String::from("no input."), pos: chars.len(), len: 0 }.into());
+    }
+    (tokens, errors)
+}
+
+pub fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(ch) if is_identifier_start(ch) => {
+            let chars: Vec<char> = chars.collect();
+            (0..chars.len()).all(|i| is_identifier_part(chars[i]) || is_inner_dot(&chars, i))
+        }
+        _ => false
     }
-    Ok(tokens)
 }
 
+fn is_identifier_start(ch: char) -> bool {
+    ch.is_alphabetic() || ch == '_'
+}
+
+fn is_identifier_part(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
+}
+
+/// A `.` belongs to an identifier only if followed by a letter, digit or `_`, so `exists x. e` works.
+fn is_inner_dot(chars: &[char], pos: usize) -> bool {
+    chars[pos] == '.' && pos + 1 < chars.len() && is_identifier_part(chars[pos + 1])
+}
+
+/// Checks whether a name following the tokens is one of the variables bound by a quantifier, i.e.
+/// whether the tokens end with a quantifier or a quantifier, names and commas.
+fn is_bound_variable(tokens: &[Token]) -> bool {
+    for (i, token) in tokens.iter().enumerate().rev() {
+        match token {
+            // `exists` is only a quantifier if followed by a name
+            Token::Variable(_, name) if name == "exists" || name == "forall" => {
+                return i + 1 == tokens.len() || matches!(tokens[i + 1], Token::Variable(..) | Token::QuotedVariable(..));
+            }
+            Token::Operator(_, op) if matches!(operator_symbol(op), "exists" | "forall") => return true,
+            Token::Variable(..) | Token::QuotedVariable(..) | Token::Comma(_) => {}
+            _ => return false
+        }
+    }
+    false
+}
 
-fn char_at(str: &str, pos: usize) -> char {
-    str.chars().nth(pos).unwrap()
+fn starts_with(chars: &[char], literal: &str) -> bool {
+    let mut rest = chars.iter();
+    literal.chars().all(|ch| rest.next() == Some(&ch))
 }
 
 /*
@@ -145,6 +494,7 @@ fn char_at(str: &str, pos: usize) -> char {
  */
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
     use super::*;
 
@@ -162,6 +512,97 @@ mod tests {
         assert_eq!(tokens, vec![Token::Variable(0, String::from("abc"))])
     }
 
+    #[test]
+    fn tokenize_parses_identifiers_with_digits_underscores_and_dots() {
+        let res = tokenize("x1 req_valid fsm.state0 _a");
+        assert_eq!(res.is_err(), false);
+        let tokens = res.unwrap();
+        assert_eq!(tokens, vec![Token::Variable(0, String::from("x1")),
+                                Token::Variable(3, String::from("req_valid")),
+                                Token::Variable(13, String::from("fsm.state0")),
+                                Token::Variable(24, String::from("_a"))]);
+    }
+
+    #[test]
+    fn tokenize_parses_values_in_front_of_identifiers() {
+        let res = tokenize("1x");
+        assert_eq!(res.is_err(), false);
+        let tokens = res.unwrap();
+        assert_eq!(tokens, vec![Token::Value(0, true), Token::Variable(1, String::from("x"))]);
+    }
+
+    #[test]
+    fn tokenize_parses_quoted_identifiers() {
+        let res = tokenize("\"bus[3]\"&a");
+        assert_eq!(res.is_err(), false);
+        let tokens = res.unwrap();
+        assert_eq!(tokens, vec![Token::QuotedVariable(0, String::from("bus[3]")),
+                                Token::Operator(8, String::from("&")),
+                                Token::Variable(9, String::from("a"))]);
+        assert_eq!(token_len(&tokens[0]), 8);
+    }
+
+    #[test]
+    fn tokenize_returns_err_for_bad_quoted_identifiers() {
+        let res = tokenize("a & \"bus");
+        assert_eq!(res.is_err(), true);
+        assert_eq!(res.err().unwrap().message, "unterminated quoted name");
+
+        let res = tokenize("\"\"");
+        assert_eq!(res.is_err(), true);
+        assert_eq!(res.err().unwrap().message, "empty quoted name");
+    }
+
+    #[test]
+    fn is_identifier_checks_identifier_syntax() {
+        assert_eq!(is_identifier("fsm.state0"), true);
+        assert_eq!(is_identifier("_1"), true);
+        assert_eq!(is_identifier("größe"), true);
+        assert_eq!(is_identifier("a."), false);
+        assert_eq!(is_identifier("a..b"), false);
+        assert_eq!(is_identifier("1a"), false);
+        assert_eq!(is_identifier("bus[3]"), false);
+        assert_eq!(is_identifier(""), false);
+    }
+
+    #[test]
+    fn tokenize_parses_non_ascii_identifiers_with_char_positions() {
+        let res = tokenize("größe & 変数");
+        assert_eq!(res.is_err(), false);
+        let tokens = res.unwrap();
+        assert_eq!(tokens, vec![Token::Variable(0, String::from("größe")),
+                                Token::Operator(6, String::from("&")),
+                                Token::Variable(8, String::from("変数"))]);
+        assert_eq!(token_len(&tokens[0]), 5);
+    }
+
+    #[test]
+    fn tokenize_reports_char_positions_after_non_ascii_text() {
+        let res = tokenize("\"bäh\" ∧ ä $");
+        assert_eq!(res.is_err(), true);
+        let err = res.err().unwrap();
+        assert_eq!(err.message, "Invalid character '$'");
+        assert_eq!(err.pos, 10);
+    }
+
+    #[test]
+    fn tokenize_parses_unicode_operators() {
+        let res = tokenize("¬a∧b → c⇔d");
+        assert_eq!(res.is_err(), false);
+        let tokens = res.unwrap();
+        assert_eq!(tokens, vec![Token::Operator(0, String::from("¬")),
+                                Token::Variable(1, String::from("a")),
+                                Token::Operator(2, String::from("∧")),
+                                Token::Variable(3, String::from("b")),
+                                Token::Operator(5, String::from("→")),
+                                Token::Variable(7, String::from("c")),
+                                Token::Operator(8, String::from("⇔")),
+                                Token::Variable(9, String::from("d"))]);
+        assert_eq!(token_name(&tokens[0]), "!");
+        assert_eq!(token_name(&tokens[4]), "=>");
+        assert_eq!(token_len(&tokens[4]), 1);
+    }
+
     #[test]
     fn tokenize_parses_two_letter_tokens() {
         let res = tokenize("=>");
@@ -170,6 +611,78 @@ mod tests {
         assert_eq!(tokens, vec![Token::Operator(0, String::from("=>"))]);
     }
 
+    #[test]
+    fn tokenize_parses_negated_and_reverse_operators() {
+        let res = tokenize("a!&b !| !^ <= !=> !a");
+        assert_eq!(res.is_err(), false);
+        let tokens = res.unwrap();
+        assert_eq!(tokens, vec![Token::Variable(0, String::from("a")),
+                                Token::Operator(1, String::from("!&")),
+                                Token::Variable(3, String::from("b")),
+                                Token::Operator(5, String::from("!|")),
+                                Token::Operator(8, String::from("!^")),
+                                Token::Operator(11, String::from("<=")),
+                                Token::Operator(14, String::from("!=>")),
+                                Token::Operator(18, String::from("!")),
+                                Token::Variable(19, String::from("a"))]);
+    }
+
+    #[test]
+    fn tokenize_parses_conditionals_and_calls() {
+        let res = tokenize("s?a:ite(s,a)");
+        assert_eq!(res.is_err(), false);
+        let tokens = res.unwrap();
+        assert_eq!(tokens, vec![Token::Variable(0, String::from("s")),
+                                Token::Operator(1, String::from("?")),
+                                Token::Variable(2, String::from("a")),
+                                Token::Operator(3, String::from(":")),
+                                Token::Variable(4, String::from("ite")),
+                                Token::OpenParanthesis(7),
+                                Token::Variable(8, String::from("s")),
+                                Token::Comma(9),
+                                Token::Variable(10, String::from("a")),
+                                Token::CloseParanthesis(11)]);
+    }
+
+    #[test]
+    fn tokenize_parses_numbers_and_semicolons() {
+        let res = tokenize("atmost(12;0,1)");
+        assert_eq!(res.is_err(), false);
+        let tokens = res.unwrap();
+        assert_eq!(tokens, vec![Token::Variable(0, String::from("atmost")),
+                                Token::OpenParanthesis(6),
+                                Token::Number(7, String::from("12")),
+                                Token::Semicolon(9),
+                                Token::Value(10, false),
+                                Token::Comma(11),
+                                Token::Value(12, true),
+                                Token::CloseParanthesis(13)]);
+    }
+
+    #[test]
+    fn tokenize_parses_quantifiers() {
+        let res = tokenize("exists x. ∀y.fsm.a");
+        assert_eq!(res.is_err(), false);
+        let tokens = res.unwrap();
+        assert_eq!(tokens, vec![Token::Variable(0, String::from("exists")),
+                                Token::Variable(7, String::from("x")),
+                                Token::Dot(8),
+                                Token::Operator(10, String::from("∀")),
+                                Token::Variable(11, String::from("y")),
+                                Token::Dot(12),
+                                Token::Variable(13, String::from("fsm.a"))]);
+        assert_eq!(token_name(&tokens[3]), "forall");
+
+        let res = tokenize("a.. b.");
+        assert_eq!(res.is_err(), false);
+        let tokens = res.unwrap();
+        assert_eq!(tokens, vec![Token::Variable(0, String::from("a")),
+                                Token::Dot(1),
+                                Token::Dot(2),
+                                Token::Variable(4, String::from("b")),
+                                Token::Dot(5)]);
+    }
+
     #[test]
     fn tokenize_parses_one_letter_tokens() {
         let res = tokenize("0");
@@ -215,8 +728,76 @@ mod tests {
 
     #[test]
     fn tokenize_returns_err_for_unknown_characters() {
-        let res = tokenize("#");
+        let res = tokenize("$");
+        assert_eq!(res.is_err(), true);
+    }
+
+    #[test]
+    fn tokenize_ignores_line_breaks_and_comments() {
+        let res = tokenize("a & # first\r\n/* second\n */ b /**/|c # last");
+        assert_eq!(res.is_err(), false);
+        let tokens = res.unwrap();
+        assert_eq!(tokens, vec![Token::Variable(0, String::from("a")),
+                                Token::Operator(2, String::from("&")),
+                                Token::Variable(27, String::from("b")),
+                                Token::Operator(33, String::from("|")),
+                                Token::Variable(34, String::from("c"))]);
+    }
+
+    #[test]
+    fn tokenize_returns_err_for_unterminated_comments_and_comment_only_input() {
+        let res = tokenize("a /* b */ & /* c */ b /* d");
+        assert_eq!(res.is_err(), true);
+        let err = res.err().unwrap();
+        assert_eq!(err.message, "unterminated comment");
+        assert_eq!(err.pos, 22);
+
+        let res = tokenize("# nothing\n/* here */");
         assert_eq!(res.is_err(), true);
+        assert_eq!(res.err().unwrap().message, "no input.");
+    }
+
+    #[test]
+    fn line_column_converts_positions() {
+        let str = "a &\r\n  b |\n\n c $";
+        assert_eq!(line_column(str, 0), (1, 1));
+        assert_eq!(line_column(str, 3), (1, 4));
+        assert_eq!(line_column(str, 7), (2, 3));
+        let err = tokenize(str).err().unwrap();
+        assert_eq!(line_column(str, err.pos), (4, 4));
+        assert_eq!(err.code, ErrorCode::InvalidCharacter);
+    }
+
+    #[test]
+    fn tokenize_all_reports_all_errors_and_hints() {
+        let (tokens, errors) = tokenize_all("a -> $b && \"\" | ~c");
+        assert_eq!(tokens, vec![Token::Variable(0, String::from("a")),
+                                Token::Operator(2, String::from("=>")),
+                                Token::Variable(6, String::from("b")),
+                                Token::Operator(8, String::from("&")),
+                                Token::Operator(14, String::from("|")),
+                                Token::Operator(16, String::from("!")),
+                                Token::Variable(17, String::from("c"))]);
+        let messages: Vec<(&str, Option<&str>)> = errors.iter().map(|err| (err.error.message.as_str(), err.help.as_deref())).collect();
+        assert_eq!(messages, vec![("unknown operator '->'", Some("did you mean `=>`?")),
+                                  ("Invalid character '$'", None),
+                                  ("unknown operator '&&'", Some("did you mean `&`?")),
+                                  ("empty quoted name", None),
+                                  ("unknown operator '~'", Some("did you mean `!`?"))]);
+        assert_eq!(errors[0].error.len, 2);
+
+        let (_, errors) = tokenize_all("a !=> b <=> c");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].help.as_deref(), Some("did you mean `=`?"));
+    }
+
+    #[test]
+    fn span_covers_the_error_across_lines() {
+        let str = "a & \"b\nc";
+        let err = tokenize(str).err().unwrap();
+        assert_eq!(err.code, ErrorCode::InvalidToken);
+        assert_eq!(err.code.as_str(), "E0002");
+        assert_eq!(err.span(str), Span { start: Location { line: 1, column: 5 }, end: Location { line: 2, column: 2 } });
     }
 
     #[test]